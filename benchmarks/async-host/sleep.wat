@@ -0,0 +1,11 @@
+;; Sleeps `count` times for `ms` milliseconds through the host, which awaits a timer when the runtime can suspend it
+(module
+  (import "env" "sleep_ms" (func $sleep_ms (param i32)))
+
+  (func (export "run") (param $count i32) (param $ms i32)
+    (block $done
+      (loop $sleep
+        (br_if $done (i32.eqz (local.get $count)))
+        (call $sleep_ms (local.get $ms))
+        (local.set $count (i32.sub (local.get $count) (i32.const 1)))
+        (br $sleep)))))
@@ -0,0 +1,19 @@
+;; Calls a host function without arguments nor results
+(module
+  ;; Every module of the suite has the same imports, linked the same way by every runtime
+  (import "env" "nop" (func $nop))
+  (import "env" "take_i32" (func $take_i32 (param i32)))
+  (import "env" "take_i64" (func $take_i64 (param i64)))
+  (import "env" "take_f64" (func $take_f64 (param f64)))
+  (import "env" "copy" (func $copy (param i32 i32)))
+  (import "env" "call_back" (func $call_back))
+
+  (memory (export "memory") 1)
+
+  ;; Called by the host from `call_back`
+  (func (export "callback"))
+
+  (func (export "run") (param $calls i32)
+    (loop $loop
+      (call $nop)
+      (br_if $loop (local.tee $calls (i32.sub (local.get $calls) (i32.const 1)))))))
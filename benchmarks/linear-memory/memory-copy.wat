@@ -0,0 +1,8 @@
+;; Copies 4 KiB from the start of the memory to its middle with `memory.copy` every iteration
+(module
+  (memory (export "memory") 1)
+
+  (func (export "run") (param $iterations i32)
+    (loop $iteration
+      (memory.copy (i32.const 32768) (i32.const 0) (i32.const 4096))
+      (br_if $iteration (local.tee $iterations (i32.sub (local.get $iterations) (i32.const 1)))))))
@@ -0,0 +1,8 @@
+;; Fills 4 KiB at the start of the memory with `memory.fill` every iteration
+(module
+  (memory (export "memory") 1)
+
+  (func (export "run") (param $iterations i32)
+    (loop $iteration
+      (memory.fill (i32.const 0) (local.get $iterations) (i32.const 4096))
+      (br_if $iteration (local.tee $iterations (i32.sub (local.get $iterations) (i32.const 1)))))))
@@ -0,0 +1,14 @@
+;; Grows the memory by nothing, which succeeds, and past its maximum, which fails, 256 times per iteration.
+;; Memory can't shrink back, so this measures the instruction rather than the allocation
+(module
+  (memory (export "memory") 1 1)
+
+  (func (export "run") (param $iterations i32)
+    (local $count i32)
+    (loop $iteration
+      (local.set $count (i32.const 128))
+      (loop $grow
+        (drop (memory.grow (i32.const 0)))
+        (drop (memory.grow (i32.const 1)))
+        (br_if $grow (local.tee $count (i32.sub (local.get $count) (i32.const 1)))))
+      (br_if $iteration (local.tee $iterations (i32.sub (local.get $iterations) (i32.const 1)))))))
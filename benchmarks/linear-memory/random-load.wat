@@ -0,0 +1,19 @@
+;; Loads 256 aligned words at random addresses every iteration.
+;; The addresses come from a linear congruential generator, whose cost is part of the measure
+(module
+  (memory (export "memory") 1)
+
+  (func (export "run") (param $iterations i32)
+    (local $state i32)
+    (local $count i32)
+    (local $sum i32)
+    (loop $iteration
+      (local.set $count (i32.const 256))
+      (loop $access
+        ;; Aligned address within the 64 KiB of the memory, out of the high bits of the generator
+        (local.set $state (i32.add (i32.mul (local.get $state) (i32.const 1103515245)) (i32.const 12345)))
+        (local.set $sum (i32.add (local.get $sum) (i32.load (i32.and (i32.shr_u (local.get $state) (i32.const 16)) (i32.const 0xfffc)))))
+        (br_if $access (local.tee $count (i32.sub (local.get $count) (i32.const 1)))))
+      (br_if $iteration (local.tee $iterations (i32.sub (local.get $iterations) (i32.const 1)))))
+    ;; Kept so that the loads aren't dead code
+    (i32.store (i32.const 0) (local.get $sum))))
@@ -0,0 +1,15 @@
+;; Loads the 256 aligned words of the first KiB of the memory one after the other every iteration
+(module
+  (memory (export "memory") 1)
+
+  (func (export "run") (param $iterations i32)
+    (local $address i32)
+    (local $sum i32)
+    (loop $iteration
+      (local.set $address (i32.const 0))
+      (loop $access
+        (local.set $sum (i32.add (local.get $sum) (i32.load (local.get $address))))
+        (br_if $access (i32.lt_u (local.tee $address (i32.add (local.get $address) (i32.const 4))) (i32.const 1024))))
+      (br_if $iteration (local.tee $iterations (i32.sub (local.get $iterations) (i32.const 1)))))
+    ;; Kept so that the loads aren't dead code
+    (i32.store (i32.const 0) (local.get $sum))))
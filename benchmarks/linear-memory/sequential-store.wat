@@ -0,0 +1,12 @@
+;; Stores the 256 aligned words of the first KiB of the memory one after the other every iteration
+(module
+  (memory (export "memory") 1)
+
+  (func (export "run") (param $iterations i32)
+    (local $address i32)
+    (loop $iteration
+      (local.set $address (i32.const 0))
+      (loop $access
+        (i32.store (local.get $address) (local.get $iterations))
+        (br_if $access (i32.lt_u (local.tee $address (i32.add (local.get $address) (i32.const 4))) (i32.const 1024))))
+      (br_if $iteration (local.tee $iterations (i32.sub (local.get $iterations) (i32.const 1)))))))
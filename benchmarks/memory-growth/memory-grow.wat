@@ -0,0 +1,15 @@
+;; Grows the memory by a page, up to the maximum it declares, and writes to the new page
+(module
+  (memory (export "memory") 1 2)
+
+  (func (export "size") (result i32)
+    (memory.size))
+
+  (func (export "grow") (param $pages i32) (result i32)
+    (local $previous i32)
+    (local.set $previous (memory.grow (local.get $pages)))
+    (if (i32.ne (local.get $previous) (i32.const -1))
+      (then
+        ;; Traps if the runtime grew the memory without backing the new pages
+        (i32.store8 (i32.sub (i32.mul (memory.size) (i32.const 65536)) (i32.const 1)) (i32.const 1))))
+    (local.get $previous)))
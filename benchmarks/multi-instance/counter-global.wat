@@ -0,0 +1,13 @@
+;; Adds `id` to a total kept in a global `iterations` times and returns it. Only the instance that owns the global
+;; adds to it, so the total after a number of calls only depends on its own `id`
+(module
+  (global $total (mut i32) (i32.const 0))
+
+  (func (export "run") (param $id i32) (param $iterations i32) (result i32)
+    (block $done
+      (loop $add
+        (br_if $done (i32.eqz (local.get $iterations)))
+        (global.set $total (i32.add (global.get $total) (local.get $id)))
+        (local.set $iterations (i32.sub (local.get $iterations) (i32.const 1)))
+        (br $add)))
+    (global.get $total)))
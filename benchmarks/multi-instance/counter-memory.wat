@@ -0,0 +1,17 @@
+;; Adds `id` to a total kept at the start and at the end of its linear memory `iterations` times and returns it. A
+;; total that depends on the `id` of another instance means that the memory is shared
+(module
+  (memory (export "memory") 1 1)
+
+  (func (export "run") (param $id i32) (param $iterations i32) (result i32)
+    (block $done
+      (loop $add
+        (br_if $done (i32.eqz (local.get $iterations)))
+        (i32.store (i32.const 0) (i32.add (i32.load (i32.const 0)) (local.get $id)))
+        (i32.store (i32.const 65532) (i32.add (i32.load (i32.const 65532)) (local.get $id)))
+        (local.set $iterations (i32.sub (local.get $iterations) (i32.const 1)))
+        (br $add)))
+    ;; Both ends of the page were written to by this instance only
+    (if (i32.ne (i32.load (i32.const 0)) (i32.load (i32.const 65532)))
+      (then (unreachable)))
+    (i32.load (i32.const 0))))
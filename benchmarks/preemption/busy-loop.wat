@@ -0,0 +1,22 @@
+;; Runs `rounds` rounds of 256 steps of a linear congruential generator, calling `checkpoint` after every round, and
+;; returns the state it ended in, which doesn't depend on how the call was sliced
+(module
+  (import "env" "checkpoint" (func $checkpoint))
+
+  (func (export "run") (param $rounds i32) (result i32)
+    (local $state i32)
+    (local $step i32)
+    (local.set $state (i32.const 1))
+    (block $done
+      (loop $round
+        (br_if $done (i32.eqz (local.get $rounds)))
+        (local.set $step (i32.const 256))
+        (loop $steps
+          (local.set $state (i32.add (i32.mul (local.get $state) (i32.const 1103515245)) (i32.const 12345)))
+          (local.set $step (i32.sub (local.get $step) (i32.const 1)))
+          (br_if $steps (local.get $step)))
+        ;; The only place where wasefire can be suspended
+        (call $checkpoint)
+        (local.set $rounds (i32.sub (local.get $rounds) (i32.const 1)))
+        (br $round)))
+    (local.get $state)))
@@ -0,0 +1,19 @@
+//! A `wasm32-wasip1` command built with Rust's std, which pulls in the imports of its startup code and stdio on top of
+//! what it calls itself. Built with:
+//!
+//! ```sh
+//! rustc --target wasm32-wasip1 -C opt-level=z -C lto -C codegen-units=1 -C panic=abort -C strip=symbols \
+//!     -C link-arg=-zstack-size=8192 -C link-arg=--initial-memory=65536 -C link-arg=--max-memory=131072 \
+//!     hello-rust.rs -o hello-rust.wasm
+//! ```
+//!
+//! The default stack of 1 MiB wouldn't fit on the boards, the second page is left for the allocator to grow into.
+
+use std::time::Instant;
+
+fn main() {
+    let start = Instant::now();
+    let args = std::env::args().count();
+    println!("Hello from Rust on wasm32-wasip1, {args} arguments");
+    eprintln!("{} ns since start", start.elapsed().as_nanos());
+}
@@ -0,0 +1,41 @@
+;; Laid out like a `wasm32-wasip1` command: asks for its arguments, reads the monotonic clock, asks for random bytes
+;; and prints a line before exiting through `proc_exit`. A call that fails exits with the number of the step instead
+(module
+  (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "args_get" (func $args_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+  (memory (export "memory") 1)
+
+  ;; 0: argc, 4: argv_buf_size, 8: argv, 16: argv_buf, 24 and 32: times, 40: random bytes,
+  ;; 56: iovec, 64: bytes written, 128: the line
+  (data (i32.const 128) "Hello from wasm32-wasip1\0a")
+
+  ;; Exits with `step` unless `errno` is 0
+  (func $check (param $errno i32) (param $step i32)
+    (if (local.get $errno)
+      (then (call $proc_exit (local.get $step)))))
+
+  (func (export "_start")
+    (call $check (call $args_sizes_get (i32.const 0) (i32.const 4)) (i32.const 1))
+    (call $check (i32.or (i32.load (i32.const 0)) (i32.load (i32.const 4))) (i32.const 2))
+    (call $check (call $args_get (i32.const 8) (i32.const 16)) (i32.const 3))
+
+    ;; Monotonic clock, 1 µs precision
+    (call $check (call $clock_time_get (i32.const 1) (i64.const 1000) (i32.const 24)) (i32.const 4))
+    (call $check (call $random_get (i32.const 40) (i32.const 16)) (i32.const 5))
+
+    (i32.store (i32.const 56) (i32.const 128))
+    (i32.store (i32.const 60) (i32.const 25))
+    (call $check (call $fd_write (i32.const 1) (i32.const 56) (i32.const 1) (i32.const 64)) (i32.const 6))
+    (call $check (i32.ne (i32.load (i32.const 64)) (i32.const 25)) (i32.const 7))
+
+    ;; The clock doesn't go back
+    (call $check (call $clock_time_get (i32.const 1) (i64.const 1000) (i32.const 32)) (i32.const 8))
+    (call $check (i64.lt_u (i64.load (i32.const 32)) (i64.load (i32.const 24))) (i32.const 9))
+
+    (call $proc_exit (i32.const 0))
+    (unreachable)))
@@ -0,0 +1,57 @@
+use crate::{Backend, SPECTEST};
+
+#[cfg(feature = "wasmtime")]
+mod wasmtime;
+
+#[cfg(feature = "wasmi")]
+mod wasmi;
+
+#[cfg(feature = "wasm-interpreter")]
+mod wasm_interpreter;
+
+#[cfg(feature = "wamr")]
+mod wamr;
+
+#[cfg(feature = "wasefire")]
+mod wasefire_interpreter;
+
+/// A runtime set up like one of the laze modules of the firmware
+pub struct Configuration {
+    /// Name of the laze module, and of the runtime in the results
+    pub name: &'static str,
+    new: fn() -> Box<dyn Backend>,
+}
+
+impl Configuration {
+    /// A fresh backend, with the `spectest` module already registered when the configuration can run it
+    pub fn backend(&self) -> Box<dyn Backend> {
+        let mut backend = (self.new)();
+        let spectest = wast::parser::ParseBuffer::new(SPECTEST)
+            .and_then(|buffer| wast::parser::parse::<wast::Wat>(&buffer)?.encode())
+            .unwrap();
+        if let Ok(instance) = backend.instantiate(&spectest) {
+            // Backends that can't link modules together simply fail the tests importing from it
+            let _ = backend.register(instance, "spectest");
+        }
+        backend
+    }
+}
+
+/// Every configuration built in, see the features of this crate.
+/// `wasmtime-no-simd` and `wamr-aot` have no counterpart here: the former is a build flag of Pulley, the latter
+/// needs payloads compiled ahead of time for the board
+pub fn configurations() -> Vec<&'static Configuration> {
+    let backends: &[&[Configuration]] = &[
+        #[cfg(feature = "wasmtime")]
+        self::wasmtime::CONFIGURATIONS,
+        #[cfg(feature = "wasmi")]
+        self::wasmi::CONFIGURATIONS,
+        #[cfg(feature = "wasm-interpreter")]
+        self::wasm_interpreter::CONFIGURATIONS,
+        #[cfg(feature = "wasefire")]
+        self::wasefire_interpreter::CONFIGURATIONS,
+        #[cfg(feature = "wamr")]
+        self::wamr::CONFIGURATIONS,
+    ];
+    backends.iter().flat_map(|configurations| configurations.iter()).collect()
+}
@@ -0,0 +1,76 @@
+use wamr_rust_sdk::{function::Function, instance::Instance as WamrInstance, module::Module, runtime::Runtime, value::WasmValue};
+
+use super::Configuration;
+use crate::{Backend, Error, Instance, Value};
+
+/// The interpreter is selected at build time, like in the firmware
+pub const CONFIGURATIONS: &[Configuration] = &[Configuration {
+    name: if cfg!(feature = "wamr-fast") { "wamr-fast" } else { "wamr" },
+    new: || Box::new(Wamr { runtime: RUNTIME.with(|runtime| *runtime), instances: Vec::new() }),
+}];
+
+thread_local! {
+    /// WAMR keeps its state in globals, so that there can only be one runtime per process, shared by the backends
+    static RUNTIME: &'static Runtime =
+        Box::leak(Box::new(Runtime::builder().use_system_allocator().run_as_interpreter().build().unwrap()));
+}
+
+/// Stack given to the Embench payloads in the firmware
+const STACK_SIZE: u32 = 4 * 1024;
+
+/// Modules borrow the runtime and instances their module. The modules are leaked, a backend only lives for a
+/// single `.wast` file or fuzzing input
+struct Wamr {
+    runtime: &'static Runtime,
+    instances: Vec<WamrInstance<'static>>,
+}
+
+impl Backend for Wamr {
+    fn instantiate(&mut self, wasm: &[u8]) -> Result<Instance, Error> {
+        let module = Module::from_vec(self.runtime, wasm.to_vec(), "harness-module")
+            .map_err(|error| Error::Invalid(format!("{error:?}")))?;
+        let module = Box::leak(Box::new(module));
+        // The start function and the initialisation of the segments can't be told apart from linking
+        let instance = WamrInstance::new(self.runtime, module, STACK_SIZE).map_err(|error| Error::Link(format!("{error:?}")))?;
+        self.instances.push(instance);
+        Ok(Instance(self.instances.len() - 1))
+    }
+
+    fn register(&mut self, _: Instance, _: &str) -> Result<(), Error> {
+        // Would need the multi-module support of WAMR, which isn't built
+        Err(Error::Unsupported("linking modules together".to_owned()))
+    }
+
+    fn invoke(&mut self, instance: Instance, name: &str, args: &[Value]) -> Result<Vec<Value>, Error> {
+        let instance = &self.instances[instance.0];
+        let function = Function::find_export_func(instance, name).map_err(|error| Error::Link(format!("{error:?}")))?;
+        let args = args.iter().map(|&arg| to_val(arg)).collect::<Vec<_>>();
+        let results = function.call(instance, &args).map_err(|error| Error::Trap(format!("{error:?}")))?;
+        results.into_iter().filter(|result| !matches!(result, WasmValue::Void)).map(from_val).collect()
+    }
+
+    fn global(&mut self, _: Instance, _: &str) -> Result<Value, Error> {
+        Err(Error::Unsupported("reading exported globals".to_owned()))
+    }
+}
+
+fn to_val(value: Value) -> WasmValue {
+    match value {
+        Value::I32(value) => WasmValue::I32(value),
+        Value::I64(value) => WasmValue::I64(value),
+        Value::F32(bits) => WasmValue::F32(f32::from_bits(bits)),
+        Value::F64(bits) => WasmValue::F64(f64::from_bits(bits)),
+        Value::V128(value) => WasmValue::V128(value as i128),
+    }
+}
+
+fn from_val(value: WasmValue) -> Result<Value, Error> {
+    match value {
+        WasmValue::I32(value) => Ok(Value::I32(value)),
+        WasmValue::I64(value) => Ok(Value::I64(value)),
+        WasmValue::F32(value) => Ok(Value::F32(value.to_bits())),
+        WasmValue::F64(value) => Ok(Value::F64(value.to_bits())),
+        WasmValue::V128(value) => Ok(Value::V128(value as u128)),
+        value => Err(Error::Unsupported(format!("{value:?} values"))),
+    }
+}
@@ -0,0 +1,85 @@
+use std::alloc::{Layout, alloc_zeroed};
+
+use wasefire_interpreter::{InstId, MEMORY_ALIGN, Module, RunResult, Store, Val, prepare};
+
+use super::Configuration;
+use crate::{Backend, Error, Instance, Value};
+
+pub const CONFIGURATIONS: &[Configuration] = &[
+    Configuration { name: "wasefire", new: || Box::new(Wasefire { store: Store::default(), instances: Vec::new() }) },
+];
+
+/// Sizes the linear memory of every instance like the firmware does
+#[path = "../../../src/wasefire_memory.rs"]
+mod wasefire_memory;
+
+/// The store borrows the modules, their memories and the names they are registered under for as long as it lives.
+/// They are leaked, a backend only lives for a single `.wast` file or fuzzing input
+struct Wasefire {
+    store: Store<'static>,
+    instances: Vec<InstId>,
+}
+
+impl Backend for Wasefire {
+    fn instantiate(&mut self, wasm: &[u8]) -> Result<Instance, Error> {
+        let size = wasefire_memory::memory_size(wasm).map_err(|error| Error::Link(error.to_string()))?;
+        let wasm = prepare(wasm).map_err(|error| Error::Invalid(format!("{error:?}")))?.leak();
+        let module = Module::new(wasm).map_err(|error| Error::Invalid(format!("{error:?}")))?;
+
+        // Allocating nothing is undefined, the interpreter is still only given `size` bytes
+        let layout = Layout::from_size_align(size.max(MEMORY_ALIGN), MEMORY_ALIGN).unwrap();
+        let memory = unsafe { core::slice::from_raw_parts_mut(alloc_zeroed(layout), size) };
+
+        let instance = self.store.instantiate(module, memory).map_err(|error| match error {
+            wasefire_interpreter::Error::Trap => Error::Trap(format!("{error:?}")),
+            error => Error::Link(format!("{error:?}")),
+        })?;
+        self.instances.push(instance);
+        Ok(Instance(self.instances.len() - 1))
+    }
+
+    fn register(&mut self, instance: Instance, name: &str) -> Result<(), Error> {
+        let name = name.to_owned().leak();
+        self.store.set_name(self.instances[instance.0], name).map_err(|error| Error::Link(format!("{error:?}")))
+    }
+
+    fn invoke(&mut self, instance: Instance, name: &str, args: &[Value]) -> Result<Vec<Value>, Error> {
+        let args = args.iter().map(|&arg| to_val(arg)).collect::<Result<Vec<_>, _>>()?;
+        let result = self.store.invoke(self.instances[instance.0], name, args).map_err(|error| match error {
+            wasefire_interpreter::Error::Trap => Error::Trap(format!("{error:?}")),
+            error => Error::Link(format!("{error:?}")),
+        })?;
+        match result {
+            RunResult::Done(results) => results.into_iter().map(from_val).collect(),
+            // Nothing is linked from the host
+            RunResult::Host(call) => Err(Error::Unsupported(format!("call to host function {}", call.index()))),
+        }
+    }
+
+    fn global(&mut self, instance: Instance, name: &str) -> Result<Value, Error> {
+        let global = self.store.get_global(self.instances[instance.0], name)
+            .map_err(|error| Error::Link(format!("{error:?}")))?;
+        from_val(global)
+    }
+}
+
+fn to_val(value: Value) -> Result<Val, Error> {
+    match value {
+        Value::I32(value) => Ok(Val::I32(value as u32)),
+        Value::I64(value) => Ok(Val::I64(value as u64)),
+        Value::F32(bits) => Ok(Val::F32(bits)),
+        Value::F64(bits) => Ok(Val::F64(bits)),
+        // The `vector-types` feature isn't enabled in the firmware
+        Value::V128(_) => Err(Error::Unsupported("v128 values".to_owned())),
+    }
+}
+
+fn from_val(value: Val) -> Result<Value, Error> {
+    match value {
+        Val::I32(value) => Ok(Value::I32(value as i32)),
+        Val::I64(value) => Ok(Value::I64(value as i64)),
+        Val::F32(bits) => Ok(Value::F32(bits)),
+        Val::F64(bits) => Ok(Value::F64(bits)),
+        value => Err(Error::Unsupported(format!("{value:?} values"))),
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use wasm::{ExternVal, Store, Value as Val, validate};
+use wasm::config::Config;
+use wasm::value::{F32, F64};
+
+use super::Configuration;
+use crate::{Backend, Error, Instance, Value};
+
+pub const CONFIGURATIONS: &[Configuration] = &[Configuration {
+    name: "wasm-interpreter",
+    new: || Box::new(WasmInterpreter { store: Store::new(NoHostState), instances: Vec::new(), registered: HashMap::new() }),
+}];
+
+/// Nothing is linked from the host
+struct NoHostState;
+
+impl Config for NoHostState {}
+
+/// The store borrows the modules for as long as it lives, they are leaked as a backend only lives for a single
+/// `.wast` file or fuzzing input
+struct WasmInterpreter {
+    store: Store<'static, NoHostState>,
+    instances: Vec<wasm::ModuleAddr>,
+    registered: HashMap<String, wasm::ModuleAddr>,
+}
+
+impl WasmInterpreter {
+    fn export(&self, instance: Instance, name: &str) -> Result<ExternVal, Error> {
+        self.store.instance_export(self.instances[instance.0], name)
+            .map_err(|error| Error::Link(format!("no export {name}: {error:?}")))
+    }
+}
+
+impl Backend for WasmInterpreter {
+    fn instantiate(&mut self, wasm: &[u8]) -> Result<Instance, Error> {
+        let wasm = wasm.to_vec().leak();
+        let validation_info = validate(wasm).map_err(|error| Error::Invalid(format!("{error:?}")))?;
+
+        // Imports are given in the order the module declares them
+        let mut imports = Vec::new();
+        for import in wasmparser::Parser::new(0).parse_all(wasm) {
+            let wasmparser::Payload::ImportSection(section) = import.map_err(|error| Error::Invalid(error.to_string()))? else {
+                continue;
+            };
+            for import in section.into_imports() {
+                let import = import.map_err(|error| Error::Invalid(error.to_string()))?;
+                let &module = self.registered.get(import.module)
+                    .ok_or_else(|| Error::Link(format!("unknown module {}", import.module)))?;
+                let value = self.store.instance_export(module, import.name)
+                    .map_err(|error| Error::Link(format!("unknown import {}.{}: {error:?}", import.module, import.name)))?;
+                imports.push(value);
+            }
+        }
+
+        let module = self.store.module_instantiate(&validation_info, imports, None)
+            .map_err(|error| Error::Link(format!("{error:?}")))?;
+        self.instances.push(module.module_addr);
+        Ok(Instance(self.instances.len() - 1))
+    }
+
+    fn register(&mut self, instance: Instance, name: &str) -> Result<(), Error> {
+        self.registered.insert(name.to_owned(), self.instances[instance.0]);
+        Ok(())
+    }
+
+    fn invoke(&mut self, instance: Instance, name: &str, args: &[Value]) -> Result<Vec<Value>, Error> {
+        let function = self.export(instance, name)?.as_func()
+            .map_err(|error| Error::Link(format!("{name} isn't a function: {error:?}")))?;
+        let args = args.iter().map(|&arg| to_val(arg)).collect();
+        let results = self.store.invoke_without_fuel(function, args).map_err(|error| Error::Trap(format!("{error:?}")))?;
+        results.into_iter().map(from_val).collect()
+    }
+
+    fn global(&mut self, instance: Instance, name: &str) -> Result<Value, Error> {
+        let global = self.export(instance, name)?.as_global()
+            .map_err(|error| Error::Link(format!("{name} isn't a global: {error:?}")))?;
+        from_val(self.store.global_read(global))
+    }
+}
+
+fn to_val(value: Value) -> Val {
+    match value {
+        Value::I32(value) => Val::I32(value as u32),
+        Value::I64(value) => Val::I64(value as u64),
+        Value::F32(bits) => Val::F32(F32(f32::from_bits(bits))),
+        Value::F64(bits) => Val::F64(F64(f64::from_bits(bits))),
+        Value::V128(value) => Val::V128(value.to_le_bytes()),
+    }
+}
+
+fn from_val(value: Val) -> Result<Value, Error> {
+    match value {
+        Val::I32(value) => Ok(Value::I32(value as i32)),
+        Val::I64(value) => Ok(Value::I64(value as i64)),
+        Val::F32(value) => Ok(Value::F32(value.0.to_bits())),
+        Val::F64(value) => Ok(Value::F64(value.0.to_bits())),
+        Val::V128(bytes) => Ok(Value::V128(u128::from_le_bytes(bytes))),
+        value => Err(Error::Unsupported(format!("{value:?} values"))),
+    }
+}
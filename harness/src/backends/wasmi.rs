@@ -0,0 +1,104 @@
+use wasmi::{CompilationMode, Config, Engine, F32, F64, Linker, Module, Store, V128, Val};
+
+use super::Configuration;
+use crate::{Backend, Error, Instance, Value};
+
+/// Variants of `src/wasmi.rs`, the modules are only validated by `wasmi-checked` and `wasmi-no-floats`
+pub const CONFIGURATIONS: &[Configuration] = &[
+    Configuration { name: "wasmi", new: || Box::new(Wasmi::new(|_| {}, false)) },
+    Configuration {
+        name: "wasmi-eager",
+        new: || Box::new(Wasmi::new(|config| { config.compilation_mode(CompilationMode::Eager); }, false)),
+    },
+    Configuration {
+        name: "wasmi-lazy",
+        new: || Box::new(Wasmi::new(|config| { config.compilation_mode(CompilationMode::Lazy); }, false)),
+    },
+    Configuration { name: "wasmi-checked", new: || Box::new(Wasmi::new(|_| {}, true)) },
+    Configuration { name: "wasmi-fuel", new: || Box::new(Wasmi::new(|config| { config.consume_fuel(true); }, false)) },
+    Configuration { name: "wasmi-no-floats", new: || Box::new(Wasmi::new(|config| { config.floats(false); }, true)) },
+];
+
+struct Wasmi {
+    store: Store<()>,
+    linker: Linker<()>,
+    instances: Vec<wasmi::Instance>,
+    checked: bool,
+}
+
+impl Wasmi {
+    fn new(variant: fn(&mut Config), checked: bool) -> Self {
+        let mut config = Config::default();
+        variant(&mut config);
+
+        let engine = Engine::new(&config);
+        let mut store = Store::new(&engine, ());
+        // As much fuel as possible, setting it fails when it isn't consumed
+        let _ = store.set_fuel(u64::MAX);
+
+        Self { linker: Linker::new(&engine), store, instances: Vec::new(), checked }
+    }
+
+    fn instance(&self, instance: Instance) -> wasmi::Instance {
+        self.instances[instance.0]
+    }
+}
+
+impl Backend for Wasmi {
+    fn instantiate(&mut self, wasm: &[u8]) -> Result<Instance, Error> {
+        let module = match self.checked {
+            true => Module::new(self.store.engine(), wasm),
+            false => unsafe { Module::new_unchecked(self.store.engine(), wasm) },
+        };
+        let module = module.map_err(|error| Error::Invalid(error.to_string()))?;
+        let instance = self.linker.instantiate_and_start(&mut self.store, &module)
+            .map_err(|error| match error.as_trap_code() {
+                Some(_) => Error::Trap(error.to_string()),
+                None => Error::Link(error.to_string()),
+            })?;
+        self.instances.push(instance);
+        Ok(Instance(self.instances.len() - 1))
+    }
+
+    fn register(&mut self, instance: Instance, name: &str) -> Result<(), Error> {
+        let instance = self.instance(instance);
+        self.linker.instance(&mut self.store, name, instance).map_err(|error| Error::Link(error.to_string()))?;
+        Ok(())
+    }
+
+    fn invoke(&mut self, instance: Instance, name: &str, args: &[Value]) -> Result<Vec<Value>, Error> {
+        let func = self.instance(instance).get_func(&self.store, name)
+            .ok_or_else(|| Error::Link(format!("no function exported as {name}")))?;
+        let args = args.iter().map(|&arg| to_val(arg)).collect::<Vec<_>>();
+        let mut results = func.ty(&self.store).results().iter().map(|&ty| Val::default(ty)).collect::<Vec<_>>();
+        func.call(&mut self.store, &args, &mut results).map_err(|error| Error::Trap(error.to_string()))?;
+        results.into_iter().map(from_val).collect()
+    }
+
+    fn global(&mut self, instance: Instance, name: &str) -> Result<Value, Error> {
+        let global = self.instance(instance).get_global(&self.store, name)
+            .ok_or_else(|| Error::Link(format!("no global exported as {name}")))?;
+        from_val(global.get(&self.store))
+    }
+}
+
+fn to_val(value: Value) -> Val {
+    match value {
+        Value::I32(value) => Val::I32(value),
+        Value::I64(value) => Val::I64(value),
+        Value::F32(bits) => Val::F32(F32::from_bits(bits)),
+        Value::F64(bits) => Val::F64(F64::from_bits(bits)),
+        Value::V128(value) => Val::V128(V128::from(value)),
+    }
+}
+
+fn from_val(value: Val) -> Result<Value, Error> {
+    match value {
+        Val::I32(value) => Ok(Value::I32(value)),
+        Val::I64(value) => Ok(Value::I64(value)),
+        Val::F32(value) => Ok(Value::F32(value.to_bits())),
+        Val::F64(value) => Ok(Value::F64(value.to_bits())),
+        Val::V128(value) => Ok(Value::V128(value.as_u128())),
+        value => Err(Error::Unsupported(format!("{:?} values", value))),
+    }
+}
@@ -0,0 +1,125 @@
+use wasmtime::{Config, Engine, Linker, Module, OptLevel, Store, Trap, Val, V128};
+
+use super::Configuration;
+use crate::{Backend, Error, Instance, Value};
+
+/// Variants of `src/wasmtime.rs`, combined with the payload profiles of `precompile_higher.rs`
+pub const CONFIGURATIONS: &[Configuration] = &[
+    Configuration { name: "wasmtime", new: || Box::new(Wasmtime::new(|_| {})) },
+    Configuration { name: "wasmtime-fuel", new: || Box::new(Wasmtime::new(|config| { config.consume_fuel(true); })) },
+    Configuration {
+        name: "wasmtime-stack-16k",
+        new: || Box::new(Wasmtime::new(|config| { config.max_wasm_stack(16 * 1024); })),
+    },
+    Configuration {
+        name: "wasmtime-growth-64k",
+        new: || Box::new(Wasmtime::new(|config| { config.memory_reservation_for_growth(64 * 1024); })),
+    },
+    Configuration {
+        name: "wasmtime-opt-none",
+        new: || Box::new(Wasmtime::new(|config| { config.cranelift_opt_level(OptLevel::None); })),
+    },
+    Configuration {
+        name: "wasmtime-opt-speed-and-size",
+        new: || Box::new(Wasmtime::new(|config| { config.cranelift_opt_level(OptLevel::SpeedAndSize); })),
+    },
+];
+
+/// Same options as `engine_config` in the firmware and `base_config` at precompilation, for 64-bit Pulley
+fn engine_config() -> Config {
+    let mut config = Config::new();
+
+    config.target("pulley64").unwrap();
+    config.cranelift_opt_level(OptLevel::Speed);
+    config.wasm_custom_page_sizes(true);
+
+    config.table_lazy_init(false);
+    config.memory_reservation(0);
+    config.memory_init_cow(false);
+    config.memory_may_move(false);
+    config.signals_based_traps(false);
+
+    // Stack given to the Embench payloads
+    config.max_wasm_stack(4096);
+    config.memory_reservation_for_growth(0);
+
+    config
+}
+
+struct Wasmtime {
+    store: Store<()>,
+    linker: Linker<()>,
+    instances: Vec<wasmtime::Instance>,
+}
+
+impl Wasmtime {
+    fn new(variant: fn(&mut Config)) -> Self {
+        let mut config = engine_config();
+        variant(&mut config);
+
+        let engine = Engine::new(&config).unwrap();
+        let mut store = Store::new(&engine, ());
+        // Like in the firmware, the fuel never runs out. Setting it fails when it isn't consumed
+        let _ = store.set_fuel(u64::MAX);
+
+        Self { linker: Linker::new(&engine), store, instances: Vec::new() }
+    }
+
+    fn instance(&self, instance: Instance) -> wasmtime::Instance {
+        self.instances[instance.0]
+    }
+}
+
+impl Backend for Wasmtime {
+    fn instantiate(&mut self, wasm: &[u8]) -> Result<Instance, Error> {
+        let module = Module::new(self.store.engine(), wasm).map_err(|error| Error::Invalid(error.to_string()))?;
+        let instance = self.linker.instantiate(&mut self.store, &module).map_err(|error| match error.is::<Trap>() {
+            true => Error::Trap(error.to_string()),
+            false => Error::Link(error.to_string()),
+        })?;
+        self.instances.push(instance);
+        Ok(Instance(self.instances.len() - 1))
+    }
+
+    fn register(&mut self, instance: Instance, name: &str) -> Result<(), Error> {
+        let instance = self.instance(instance);
+        self.linker.instance(&mut self.store, name, instance).map_err(|error| Error::Link(error.to_string()))?;
+        Ok(())
+    }
+
+    fn invoke(&mut self, instance: Instance, name: &str, args: &[Value]) -> Result<Vec<Value>, Error> {
+        let func = self.instance(instance).get_func(&mut self.store, name)
+            .ok_or_else(|| Error::Link(format!("no function exported as {name}")))?;
+        let args = args.iter().map(|&arg| to_val(arg)).collect::<Vec<_>>();
+        let mut results = func.ty(&self.store).results().map(|ty| Val::default_for_ty(&ty).unwrap()).collect::<Vec<_>>();
+        func.call(&mut self.store, &args, &mut results).map_err(|error| Error::Trap(error.to_string()))?;
+        results.into_iter().map(from_val).collect()
+    }
+
+    fn global(&mut self, instance: Instance, name: &str) -> Result<Value, Error> {
+        let global = self.instance(instance).get_global(&mut self.store, name)
+            .ok_or_else(|| Error::Link(format!("no global exported as {name}")))?;
+        from_val(global.get(&mut self.store))
+    }
+}
+
+fn to_val(value: Value) -> Val {
+    match value {
+        Value::I32(value) => Val::I32(value),
+        Value::I64(value) => Val::I64(value),
+        Value::F32(bits) => Val::F32(bits),
+        Value::F64(bits) => Val::F64(bits),
+        Value::V128(value) => Val::V128(V128::from(value)),
+    }
+}
+
+fn from_val(value: Val) -> Result<Value, Error> {
+    match value {
+        Val::I32(value) => Ok(Value::I32(value)),
+        Val::I64(value) => Ok(Value::I64(value)),
+        Val::F32(bits) => Ok(Value::F32(bits)),
+        Val::F64(bits) => Ok(Value::F64(bits)),
+        Val::V128(value) => Ok(Value::V128(value.as_u128())),
+        value => Err(Error::Unsupported(format!("{:?} values", value))),
+    }
+}
@@ -0,0 +1,258 @@
+use std::{fs, io, io::Write, panic, path::PathBuf};
+use arbitrary::Unstructured;
+use clap::{Parser, builder::PossibleValuesParser};
+use miette::Diagnostic;
+use wasmparser::{ExternalKind, Payload, ValType};
+
+use ariel_runtime_harness::{Configuration, Error as RuntimeError, Value, configurations};
+
+/// Generates modules with wasm-smith, calls their exported functions on every runtime and reports the modules the
+/// runtimes disagree on
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Runtimes to compare, comma separated or `all`. `all` leaves out `wasmi-no-floats`, naming it generates modules
+    /// without floats
+    #[arg(short, long, value_delimiter = ',', default_value = "all", value_parser = runtime_names())]
+    runtime: Vec<String>,
+
+    /// Seed of the first module, the following modules use the following seeds
+    #[arg(short, long, default_value_t = 0)]
+    seed: u64,
+
+    /// Number of modules to generate
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    iterations: u64,
+
+    /// Loop iterations and calls a module may run before trapping, so that every generated module terminates
+    #[arg(long, default_value_t = 1000)]
+    fuel: u32,
+
+    /// Random bytes each module is generated from, more bytes giving larger modules
+    #[arg(long, default_value_t = 4096)]
+    size: usize,
+
+    /// Directory the modules the runtimes disagree on are written to, as `<seed>.wasm`
+    #[arg(short, long, default_value = "results/fuzz")]
+    output: PathBuf,
+}
+
+#[derive(Debug, thiserror::Error, Diagnostic)]
+enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+fn runtime_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(configurations().iter().map(|configuration| configuration.name).chain(["all"]))
+}
+
+/// Rejects the float instructions the other runtimes accept, so it is only compared on modules without floats
+const NO_FLOATS: &str = "wasmi-no-floats";
+
+/// Memory the wasefire backend gives every instance, larger memories would only diverge because of it
+const MEMORY_SIZE: u64 = 2 * 64 * 1024;
+
+/// Restricts the generated modules to what every runtime supports in the firmware: no imports, a single memory,
+/// and none of the proposals a runtime lacks. NaNs are canonicalized, their payloads being nondeterministic
+fn config(floats: bool) -> wasm_smith::Config {
+    wasm_smith::Config {
+        allow_floats: floats,
+        max_imports: 0,
+        min_funcs: 1,
+        export_everything: true,
+        max_memories: 1,
+        max_memory32_bytes: MEMORY_SIZE,
+        max_tables: 1,
+        canonicalize_nans: true,
+        bulk_memory_enabled: true,
+        multi_value_enabled: true,
+        saturating_float_to_int_enabled: true,
+        sign_extension_ops_enabled: true,
+        reference_types_enabled: false,
+        simd_enabled: false,
+        relaxed_simd_enabled: false,
+        threads_enabled: false,
+        exceptions_enabled: false,
+        tail_call_enabled: false,
+        memory64_enabled: false,
+        extended_const_enabled: false,
+        custom_page_sizes_enabled: false,
+        wide_arithmetic_enabled: false,
+        gc_enabled: false,
+        ..wasm_smith::Config::default()
+    }
+}
+
+/// The input of wasm-smith for `seed`, from SplitMix64 so that a seed is enough to reproduce a module
+fn input(seed: u64, size: usize) -> Vec<u8> {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    (0..size.div_ceil(8)).flat_map(|_| next().to_le_bytes()).take(size).collect()
+}
+
+/// A module generated from `seed`, whose loops and calls consume a global fuel counter
+fn generate(seed: u64, args: &Args, floats: bool) -> Option<Vec<u8>> {
+    let input = input(seed, args.size);
+    let mut module = wasm_smith::Module::new(config(floats), &mut Unstructured::new(&input)).ok()?;
+    module.ensure_termination(args.fuel).ok()?;
+    Some(module.to_bytes())
+}
+
+/// Exported functions of `wasm` in the order of the export section, with zeroed arguments
+fn exports(wasm: &[u8]) -> Vec<(String, Vec<Value>)> {
+    let mut types = Vec::new();
+    let mut functions = Vec::new();
+    let mut exports = Vec::new();
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        match payload {
+            Ok(Payload::TypeSection(section)) => types.extend(section.into_iter_err_on_gc_types().flatten()),
+            Ok(Payload::FunctionSection(section)) => functions.extend(section.into_iter().flatten()),
+            Ok(Payload::ExportSection(section)) => {
+                // The modules have no imports, so that function indices start with the function section
+                for export in section.into_iter().flatten().filter(|export| export.kind == ExternalKind::Func) {
+                    let Some(ty) = functions.get(export.index as usize).and_then(|&ty| types.get(ty as usize)) else {
+                        continue;
+                    };
+                    let args = ty.params().iter().map(|&param| zero(param)).collect::<Option<Vec<_>>>();
+                    if let Some(args) = args {
+                        exports.push((export.name.to_owned(), args));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    exports
+}
+
+fn zero(ty: ValType) -> Option<Value> {
+    match ty {
+        ValType::I32 => Some(Value::I32(0)),
+        ValType::I64 => Some(Value::I64(0)),
+        ValType::F32 => Some(Value::F32(0)),
+        ValType::F64 => Some(Value::F64(0)),
+        ValType::V128 => Some(Value::V128(0)),
+        ValType::Ref(_) => None,
+    }
+}
+
+/// Which kind of error a runtime reported. Messages differ between runtimes even when they agree
+fn kind(error: &RuntimeError) -> &'static str {
+    match error {
+        RuntimeError::Invalid(_) => "rejected",
+        RuntimeError::Link(_) => "failed to instantiate",
+        RuntimeError::Trap(_) => "trapped",
+        RuntimeError::Unsupported(_) => "unsupported",
+    }
+}
+
+/// What a runtime did with a module, compared across runtimes
+#[derive(Debug, PartialEq)]
+enum Behaviour {
+    /// Results or errors of the exported functions, called in order on the same instance
+    Ran(Vec<Result<Vec<Value>, &'static str>>),
+    /// Instantiating the module, or running its start function, failed
+    Failed(&'static str),
+    Panicked,
+}
+
+fn run(configuration: &Configuration, wasm: &[u8], exports: &[(String, Vec<Value>)]) -> Behaviour {
+    let run = || {
+        let mut backend = configuration.backend();
+        let instance = match backend.instantiate(wasm) {
+            Ok(instance) => instance,
+            Err(error) => return Behaviour::Failed(kind(&error)),
+        };
+        let calls = exports.iter()
+            .map(|(name, args)| backend.invoke(instance, name, args).map_err(|error| kind(&error)))
+            .collect();
+        Behaviour::Ran(calls)
+    };
+    // The runtimes share the process, a panic only loses this module for this runtime
+    panic::catch_unwind(panic::AssertUnwindSafe(run)).unwrap_or(Behaviour::Panicked)
+}
+
+/// The first point where the behaviours differ, as one line per group of runtimes which agree
+fn describe(groups: &[(Behaviour, Vec<&str>)], exports: &[(String, Vec<Value>)]) -> Vec<String> {
+    let call = (0..exports.len()).find(|&index| {
+        let calls = groups.iter().filter_map(|(behaviour, _)| match behaviour {
+            Behaviour::Ran(calls) => Some(&calls[index]),
+            _ => None,
+        });
+        calls.clone().zip(calls.skip(1)).any(|(a, b)| a != b)
+    });
+    groups.iter()
+        .map(|(behaviour, runtimes)| {
+            let description = match (behaviour, call) {
+                (Behaviour::Ran(calls), Some(index)) => match &calls[index] {
+                    Ok(results) => format!("`{}` returned {results:?}", exports[index].0),
+                    Err(kind) => format!("`{}` {kind}", exports[index].0),
+                },
+                (Behaviour::Ran(_), None) => "ran every export".to_owned(),
+                (Behaviour::Failed(kind), _) => format!("module {kind}"),
+                (Behaviour::Panicked, _) => "panicked".to_owned(),
+            };
+            format!("{}: {description}", runtimes.join(", "))
+        })
+        .collect()
+}
+
+fn main() -> miette::Result<()> {
+    let args = Args::parse();
+    let runtimes = configurations().into_iter()
+        .filter(|configuration| {
+            args.runtime.iter()
+                .any(|name| *name == configuration.name || (name == "all" && configuration.name != NO_FLOATS))
+        })
+        .collect::<Vec<_>>();
+    // Every runtime compared has to accept the modules, otherwise they would only diverge because of it
+    let floats = runtimes.iter().all(|configuration| configuration.name != NO_FLOATS);
+
+    let mut generated = 0;
+    let mut diverging = 0;
+    for seed in args.seed..args.seed + args.iterations {
+        let Some(wasm) = generate(seed, &args, floats) else {
+            continue;
+        };
+        generated += 1;
+        // Printed first, a runtime overflowing its stack takes the whole process down
+        print!("seed {seed}");
+        io::stdout().flush().map_err(Error::from)?;
+
+        let exports = exports(&wasm);
+        let mut groups = Vec::<(Behaviour, Vec<&str>)>::new();
+        for runtime in &runtimes {
+            let behaviour = run(runtime, &wasm, &exports);
+            match groups.iter_mut().find(|(other, _)| *other == behaviour) {
+                Some((_, names)) => names.push(runtime.name),
+                None => groups.push((behaviour, vec![runtime.name])),
+            }
+        }
+
+        if groups.len() == 1 {
+            print!("\r");
+            continue;
+        }
+        diverging += 1;
+        println!(": runtimes disagree");
+        for line in describe(&groups, &exports) {
+            println!("    {line}");
+        }
+        fs::create_dir_all(&args.output).map_err(Error::from)?;
+        fs::write(args.output.join(format!("{seed}.wasm")), &wasm).map_err(Error::from)?;
+    }
+
+    println!(
+        "\n{generated} modules run on {} runtimes, {diverging} with diverging behaviour{}",
+        runtimes.len(),
+        if diverging > 0 { format!(", written to {}", args.output.display()) } else { String::new() },
+    );
+    Ok(())
+}
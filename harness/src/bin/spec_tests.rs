@@ -0,0 +1,420 @@
+use std::{collections::{BTreeMap, HashMap}, env, fs, io, io::Write, path::{Path, PathBuf}, process};
+use clap::{Parser, builder::PossibleValuesParser};
+use miette::Diagnostic;
+use wast::{QuoteWat, Wast, WastArg, WastDirective, WastExecute, WastRet, Wat};
+use wast::core::{NanPattern, V128Pattern, WastArgCore, WastRetCore};
+use wast::parser::{self, ParseBuffer};
+
+use ariel_runtime_harness::{Backend, Configuration, Error as RuntimeError, Instance, Value, configurations};
+
+/// Runs the WebAssembly spec tests against the runtimes configured like in the firmware, and writes how many
+/// assertions of every proposal each of them passes
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Checkout of https://github.com/WebAssembly/testsuite
+    #[arg(long)]
+    testsuite: PathBuf,
+
+    /// Runtimes to test, comma separated or `all`
+    #[arg(short, long, value_delimiter = ',', default_value = "all", value_parser = runtime_names())]
+    runtime: Vec<String>,
+
+    /// Proposals to test, comma separated, the top-level tests being `core`. Defaults to every proposal
+    #[arg(short, long, value_delimiter = ',')]
+    proposal: Vec<String>,
+
+    /// File the conformance matrix is written to, as `<proposal>, <runtime>, <passed>, <failed>, <skipped>` rows
+    #[arg(short, long, default_value = "results/spec/conformance.txt")]
+    output: PathBuf,
+
+    /// Print every failed assertion
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Run this single file with the first runtime and print the outcome of every directive. Every file runs in
+    /// its own process this way, so that a runtime crashing or overflowing its stack only loses that file
+    #[arg(long, hide = true)]
+    single: Option<PathBuf>,
+}
+
+#[derive(Debug, thiserror::Error, Diagnostic)]
+enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("No .wast file found in {0}")]
+    #[diagnostic(help("Point --testsuite to a checkout of https://github.com/WebAssembly/testsuite"))]
+    NoTests(PathBuf),
+    #[error("Failed to parse {0}: {1}")]
+    Wast(PathBuf, wast::Error),
+}
+
+fn runtime_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(configurations().iter().map(|configuration| configuration.name).chain(["all"]))
+}
+
+/// What became of one directive of a `.wast` file
+enum Outcome {
+    Passed,
+    Failed(String),
+    /// Needs something the harness doesn't model, such as reference values, threads or components
+    Skipped,
+}
+
+impl From<RuntimeError> for Outcome {
+    fn from(error: RuntimeError) -> Self {
+        match error {
+            RuntimeError::Unsupported(_) => Outcome::Skipped,
+            error => Outcome::Failed(error.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Counts {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+impl Counts {
+    fn add(&mut self, other: Counts) {
+        self.passed += other.passed;
+        self.failed += other.failed;
+        self.skipped += other.skipped;
+    }
+}
+
+/// Instances created by the directives of a single file
+struct Session {
+    backend: Box<dyn Backend>,
+    current: Option<Instance>,
+    named: HashMap<String, Instance>,
+}
+
+impl Session {
+    fn instance(&self, id: Option<wast::token::Id>) -> Result<Instance, RuntimeError> {
+        match id {
+            Some(id) => self.named.get(id.name()).copied(),
+            None => self.current,
+        }
+        .ok_or_else(|| RuntimeError::Link("the module wasn't instantiated".to_owned()))
+    }
+
+    fn execute(&mut self, exec: WastExecute) -> Result<Vec<Value>, RuntimeError> {
+        match exec {
+            WastExecute::Invoke(invoke) => {
+                let instance = self.instance(invoke.module)?;
+                let args = invoke.args.iter().map(argument).collect::<Result<Vec<_>, _>>()?;
+                self.backend.invoke(instance, invoke.name, &args)
+            }
+            WastExecute::Get { module, global, .. } => {
+                let instance = self.instance(module)?;
+                Ok(vec![self.backend.global(instance, global)?])
+            }
+            WastExecute::Wat(mut wat) => {
+                self.backend.instantiate(&encode(&mut wat)?)?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn run(&mut self, directive: WastDirective) -> Outcome {
+        match directive {
+            WastDirective::Module(QuoteWat::Wat(Wat::Module(mut module))) => {
+                let name = module.id.map(|id| id.name().to_owned());
+                let result = module.encode().map_err(|error| RuntimeError::Unsupported(error.to_string()))
+                    .and_then(|wasm| self.backend.instantiate(&wasm));
+                match result {
+                    Ok(instance) => {
+                        self.current = Some(instance);
+                        if let Some(name) = name {
+                            self.named.insert(name, instance);
+                        }
+                        Outcome::Passed
+                    }
+                    Err(error) => {
+                        self.current = None;
+                        error.into()
+                    }
+                }
+            }
+            WastDirective::Register { name, module, .. } => {
+                match self.instance(module).and_then(|instance| self.backend.register(instance, name)) {
+                    Ok(()) => Outcome::Passed,
+                    Err(error) => error.into(),
+                }
+            }
+            WastDirective::Invoke(invoke) => match self.execute(WastExecute::Invoke(invoke)) {
+                Ok(_) => Outcome::Passed,
+                Err(error) => error.into(),
+            },
+            WastDirective::AssertReturn { exec, results, .. } => match self.execute(exec) {
+                Ok(values) => compare(&values, &results),
+                Err(error) => error.into(),
+            },
+            WastDirective::AssertTrap { exec, .. } => {
+                let instantiating = matches!(exec, WastExecute::Wat(_));
+                match self.execute(exec) {
+                    Ok(values) => Outcome::Failed(format!("expected a trap, got {values:?}")),
+                    Err(RuntimeError::Trap(_)) => Outcome::Passed,
+                    // Traps happening while instantiating aren't always told apart from linking errors
+                    Err(RuntimeError::Link(_)) if instantiating => Outcome::Passed,
+                    Err(error) => error.into(),
+                }
+            }
+            WastDirective::AssertExhaustion { call, .. } => match self.execute(WastExecute::Invoke(call)) {
+                Ok(values) => Outcome::Failed(format!("expected the stack to be exhausted, got {values:?}")),
+                Err(RuntimeError::Trap(_)) => Outcome::Passed,
+                Err(error) => error.into(),
+            },
+            // Malformed text is about the text format, only malformed binaries are about the runtime
+            WastDirective::AssertInvalid { module: QuoteWat::Wat(mut wat), .. }
+            | WastDirective::AssertMalformed { module: QuoteWat::Wat(mut wat), .. } => {
+                match encode(&mut wat).and_then(|wasm| self.backend.instantiate(&wasm)) {
+                    Ok(_) => Outcome::Failed("the module was accepted".to_owned()),
+                    Err(RuntimeError::Invalid(_)) => Outcome::Passed,
+                    Err(error) => error.into(),
+                }
+            }
+            WastDirective::AssertUnlinkable { mut module, .. } => {
+                match encode(&mut module).and_then(|wasm| self.backend.instantiate(&wasm)) {
+                    Ok(_) => Outcome::Failed("the module was linked".to_owned()),
+                    Err(RuntimeError::Link(_) | RuntimeError::Invalid(_)) => Outcome::Passed,
+                    Err(error) => error.into(),
+                }
+            }
+            _ => Outcome::Skipped,
+        }
+    }
+}
+
+fn encode(wat: &mut Wat) -> Result<Vec<u8>, RuntimeError> {
+    match wat {
+        Wat::Module(_) => wat.encode().map_err(|error| RuntimeError::Unsupported(error.to_string())),
+        Wat::Component(_) => Err(RuntimeError::Unsupported("components".to_owned())),
+    }
+}
+
+fn argument(arg: &WastArg) -> Result<Value, RuntimeError> {
+    match arg {
+        WastArg::Core(WastArgCore::I32(value)) => Ok(Value::I32(*value)),
+        WastArg::Core(WastArgCore::I64(value)) => Ok(Value::I64(*value)),
+        WastArg::Core(WastArgCore::F32(value)) => Ok(Value::F32(value.bits)),
+        WastArg::Core(WastArgCore::F64(value)) => Ok(Value::F64(value.bits)),
+        WastArg::Core(WastArgCore::V128(value)) => Ok(Value::V128(u128::from_le_bytes(value.to_le_bytes()))),
+        arg => Err(RuntimeError::Unsupported(format!("{arg:?} arguments"))),
+    }
+}
+
+fn compare(values: &[Value], expected: &[WastRet]) -> Outcome {
+    if values.len() != expected.len() {
+        return Outcome::Failed(format!("expected {} results, got {values:?}", expected.len()));
+    }
+    for (value, expected) in values.iter().zip(expected) {
+        let WastRet::Core(expected) = expected else {
+            return Outcome::Skipped;
+        };
+        match matches(value, expected) {
+            Some(true) => {}
+            Some(false) => return Outcome::Failed(format!("expected {expected:?}, got {value:?}")),
+            None => return Outcome::Skipped,
+        }
+    }
+    Outcome::Passed
+}
+
+/// Whether `value` is the expected one, `None` when the expectation can't be checked by the harness
+fn matches(value: &Value, expected: &WastRetCore) -> Option<bool> {
+    Some(match (value, expected) {
+        (Value::I32(value), WastRetCore::I32(expected)) => value == expected,
+        (Value::I64(value), WastRetCore::I64(expected)) => value == expected,
+        (Value::F32(bits), WastRetCore::F32(expected)) => f32_matches(*bits, expected),
+        (Value::F64(bits), WastRetCore::F64(expected)) => f64_matches(*bits, expected),
+        (Value::V128(value), WastRetCore::V128(expected)) => v128_matches(value.to_le_bytes(), expected),
+        (value, WastRetCore::Either(alternatives)) => {
+            let mut outcomes = alternatives.iter().map(|expected| matches(value, expected));
+            outcomes.try_fold(false, |found, outcome| Some(found || outcome?))?
+        }
+        (_, WastRetCore::I32(_) | WastRetCore::I64(_) | WastRetCore::F32(_) | WastRetCore::F64(_) | WastRetCore::V128(_)) => {
+            false
+        }
+        _ => return None,
+    })
+}
+
+fn f32_matches(bits: u32, expected: &NanPattern<wast::token::F32>) -> bool {
+    match expected {
+        NanPattern::CanonicalNan => bits & 0x7fff_ffff == 0x7fc0_0000,
+        NanPattern::ArithmeticNan => bits & 0x7fc0_0000 == 0x7fc0_0000,
+        NanPattern::Value(expected) => bits == expected.bits,
+    }
+}
+
+fn f64_matches(bits: u64, expected: &NanPattern<wast::token::F64>) -> bool {
+    match expected {
+        NanPattern::CanonicalNan => bits & 0x7fff_ffff_ffff_ffff == 0x7ff8_0000_0000_0000,
+        NanPattern::ArithmeticNan => bits & 0x7ff8_0000_0000_0000 == 0x7ff8_0000_0000_0000,
+        NanPattern::Value(expected) => bits == expected.bits,
+    }
+}
+
+fn v128_matches(bytes: [u8; 16], expected: &V128Pattern) -> bool {
+    let lanes = |size: usize| bytes.chunks(size).map(|lane| {
+        lane.iter().rev().fold(0_u64, |value, &byte| value << 8 | byte as u64)
+    });
+    match expected {
+        V128Pattern::I8x16(expected) => lanes(1).zip(expected).all(|(lane, &expected)| lane == expected as u8 as u64),
+        V128Pattern::I16x8(expected) => lanes(2).zip(expected).all(|(lane, &expected)| lane == expected as u16 as u64),
+        V128Pattern::I32x4(expected) => lanes(4).zip(expected).all(|(lane, &expected)| lane == expected as u32 as u64),
+        V128Pattern::I64x2(expected) => lanes(8).zip(expected).all(|(lane, &expected)| lane == expected as u64),
+        V128Pattern::F32x4(expected) => lanes(4).zip(expected).all(|(lane, expected)| f32_matches(lane as u32, expected)),
+        V128Pattern::F64x2(expected) => lanes(8).zip(expected).all(|(lane, expected)| f64_matches(lane, expected)),
+    }
+}
+
+/// Runs every directive of `path` with `configuration`, printing a line for each of them
+fn run_single(path: &Path, configuration: &Configuration) -> Result<(), Error> {
+    let text = fs::read_to_string(path)?;
+    let buffer = ParseBuffer::new(&text).map_err(|error| Error::Wast(path.to_owned(), error))?;
+    let wast = parser::parse::<Wast>(&buffer).map_err(|error| Error::Wast(path.to_owned(), error))?;
+
+    let mut session = Session { backend: configuration.backend(), current: None, named: HashMap::new() };
+    let mut stdout = io::stdout().lock();
+    for directive in wast.directives {
+        let (line, _) = directive.span().linecol_in(&text);
+        match session.run(directive) {
+            Outcome::Passed => writeln!(stdout, "passed")?,
+            Outcome::Failed(reason) => writeln!(stdout, "failed {}: {}", line + 1, reason.replace('\n', " "))?,
+            Outcome::Skipped => writeln!(stdout, "skipped")?,
+        }
+        // Everything up to a crash must be seen by the parent process
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+/// Runs `path` with `configuration` in a child process. Directives the child didn't get to count as failed
+fn run_file(path: &Path, configuration: &Configuration, verbose: bool) -> Result<Counts, Error> {
+    let text = fs::read_to_string(path)?;
+    let buffer = ParseBuffer::new(&text).map_err(|error| Error::Wast(path.to_owned(), error))?;
+    let directives = parser::parse::<Wast>(&buffer).map_err(|error| Error::Wast(path.to_owned(), error))?.directives.len();
+
+    let output = process::Command::new(env::current_exe()?)
+        .args(["--testsuite", "-", "--runtime", configuration.name, "--single"])
+        .arg(path)
+        .stderr(process::Stdio::null())
+        .output()?;
+
+    let mut counts = Counts::default();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        match line.split_once(' ') {
+            _ if line == "passed" => counts.passed += 1,
+            _ if line == "skipped" => counts.skipped += 1,
+            Some(("failed", reason)) => {
+                counts.failed += 1;
+                if verbose {
+                    println!("  {} {}:{reason}", configuration.name, path.display());
+                }
+            }
+            _ => {}
+        }
+    }
+    let missing = directives.saturating_sub(counts.passed + counts.failed + counts.skipped);
+    if missing > 0 {
+        println!("  {} {}: stopped with {}, {missing} directives not run", configuration.name, path.display(), output.status);
+        counts.failed += missing;
+    }
+    Ok(counts)
+}
+
+/// Every `.wast` file of the testsuite, by proposal
+fn test_files(testsuite: &Path) -> Result<BTreeMap<String, Vec<PathBuf>>, Error> {
+    let mut files = BTreeMap::<String, Vec<PathBuf>>::new();
+    let mut dirs = vec![testsuite.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "wast") {
+                let relative = path.strip_prefix(testsuite).unwrap().iter().map(|part| part.to_string_lossy()).collect::<Vec<_>>();
+                let proposal = match &relative[..] {
+                    [_] => "core".to_owned(),
+                    [proposals, proposal, ..] if proposals == "proposals" => proposal.to_string(),
+                    [dir, ..] => dir.to_string(),
+                    [] => unreachable!(),
+                };
+                files.entry(proposal).or_default().push(path);
+            }
+        }
+    }
+    for paths in files.values_mut() {
+        paths.sort();
+    }
+    Ok(files)
+}
+
+/// Prints the share of the assertions run that passed, proposals as rows and runtimes as columns
+fn print_matrix(runtimes: &[&Configuration], matrix: &BTreeMap<String, BTreeMap<&str, Counts>>) {
+    let width = matrix.keys().map(String::len).max().unwrap_or(0).max("proposal".len());
+    print!("\n{:<width$}", "proposal");
+    for runtime in runtimes {
+        print!(" | {:>11}", runtime.name);
+    }
+    println!();
+    for (proposal, counts) in matrix {
+        print!("{proposal:<width$}");
+        for runtime in runtimes {
+            let counts = counts[runtime.name];
+            let cell = format!("{}/{}", counts.passed, counts.passed + counts.failed);
+            print!(" | {:>width$}", cell, width = runtime.name.len().max(11));
+        }
+        println!();
+    }
+}
+
+fn main() -> miette::Result<()> {
+    let args = Args::parse();
+    let runtimes = configurations().into_iter()
+        .filter(|configuration| args.runtime.iter().any(|name| name == "all" || name == configuration.name))
+        .collect::<Vec<_>>();
+
+    if let Some(path) = &args.single {
+        run_single(path, runtimes[0])?;
+        return Ok(());
+    }
+
+    let mut files = test_files(&args.testsuite)?;
+    if !args.proposal.is_empty() {
+        files.retain(|proposal, _| args.proposal.contains(proposal));
+    }
+    if files.is_empty() {
+        return Err(Error::NoTests(args.testsuite).into());
+    }
+
+    let mut matrix = BTreeMap::<String, BTreeMap<&str, Counts>>::new();
+    for runtime in &runtimes {
+        println!("{}", runtime.name);
+        for (proposal, paths) in &files {
+            let counts = matrix.entry(proposal.clone()).or_default().entry(runtime.name).or_default();
+            for path in paths {
+                counts.add(run_file(path, runtime, args.verbose)?);
+            }
+        }
+    }
+    print_matrix(&runtimes, &matrix);
+
+    if let Some(dir) = args.output.parent() {
+        fs::create_dir_all(dir).map_err(Error::from)?;
+    }
+    let mut output = fs::File::create(&args.output).map_err(Error::from)?;
+    writeln!(output, "# testsuite, {}", args.testsuite.display()).map_err(Error::from)?;
+    for (proposal, counts) in &matrix {
+        for (runtime, counts) in counts {
+            writeln!(output, "{proposal}, {runtime}, {}, {}, {}", counts.passed, counts.failed, counts.skipped)
+                .map_err(Error::from)?;
+        }
+    }
+    Ok(())
+}
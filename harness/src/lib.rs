@@ -0,0 +1,74 @@
+//! Runs the WebAssembly runtimes benchmarked on the boards on the host, with the configurations of the firmware.
+//!
+//! Every configuration is a [`Backend`], named like the `--runtime` of `scripts/run_benchmarks.rs`, which the
+//! tools of this crate drive through the same small interface.
+
+mod backends;
+
+pub use backends::{Configuration, configurations};
+
+/// A value passed to or returned by WebAssembly. Floats are kept as bits, so that NaN payloads can be compared
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+    V128(u128),
+}
+
+/// Instance created by a [`Backend`], only meaningful to the backend which created it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instance(pub usize);
+
+/// Reasons a runtime refused or failed to run something
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid module: {0}")]
+    Invalid(String),
+
+    #[error("instantiation failed: {0}")]
+    Link(String),
+
+    #[error("trap: {0}")]
+    Trap(String),
+
+    #[error("not supported: {0}")]
+    Unsupported(String),
+}
+
+/// One runtime configuration, and the instances created with it so far
+pub trait Backend {
+    /// Validates `wasm` as the configuration does in the firmware, then instantiates it and runs its start function.
+    /// Imports are resolved from the instances given to [`Backend::register`]
+    fn instantiate(&mut self, wasm: &[u8]) -> Result<Instance, Error>;
+
+    /// Makes the exports of `instance` available to later instances, under the module name `name`
+    fn register(&mut self, instance: Instance, name: &str) -> Result<(), Error>;
+
+    /// Calls the function exported as `name`
+    fn invoke(&mut self, instance: Instance, name: &str, args: &[Value]) -> Result<Vec<Value>, Error>;
+
+    /// Reads the global exported as `name`
+    fn global(&mut self, instance: Instance, name: &str) -> Result<Value, Error>;
+}
+
+/// The `spectest` module the spec tests import from. Its functions do nothing, so that it can be a plain module
+/// for every runtime instead of host functions
+pub const SPECTEST: &str = r#"
+(module
+  (global (export "global_i32") i32 (i32.const 666))
+  (global (export "global_i64") i64 (i64.const 666))
+  (global (export "global_f32") f32 (f32.const 666.6))
+  (global (export "global_f64") f64 (f64.const 666.6))
+  (table (export "table") 10 20 funcref)
+  (memory (export "memory") 1 2)
+  (func (export "print"))
+  (func (export "print_i32") (param i32))
+  (func (export "print_i64") (param i64))
+  (func (export "print_f32") (param f32))
+  (func (export "print_f64") (param f64))
+  (func (export "print_i32_f32") (param i32 f32))
+  (func (export "print_f64_f64") (param f64 f64))
+)
+"#;
@@ -10,42 +10,118 @@ miette = { version = "7.2", features = ["fancy"] }
 thiserror = { version = "2.0" }
 ---
 
-use std::{fs, io};
-use clap::Parser;
+use std::{env, fs, io};
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus};
+use clap::{Parser, ValueEnum, builder::PossibleValue};
 use miette::Diagnostic;
 
 
-/// Helper script to precompile the benchmarks using the selected wasmtime version
+/// Helper script to precompile the benchmarks using the selected wasmtime versions
+///
+/// Payloads are written to `benchmarks/<suite>/wasmtime-<version>/<target>/<profile>/`
+/// next to a `manifest.toml` describing how they were produced. Only modules whose
+/// `.wasm` source changed since the last run are precompiled again.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Wasmtime version to use
-    #[arg(short = 'w', long = "w-version")]
-    wasmtime_version: String
+    /// Wasmtime version to use, can be repeated
+    #[arg(short = 'w', long = "w-version", required = true)]
+    wasmtime_versions: Vec<String>,
+
+    /// Configuration profile to precompile with, can be repeated. Defaults to every profile
+    #[arg(short, long = "profile")]
+    profiles: Vec<String>,
+
+    /// Pulley target to precompile for
+    #[arg(short, long, default_value = "pulley32")]
+    target: Target,
+
+    /// Precompile every module, even the ones whose source didn't change
+    #[arg(short, long)]
+    force: bool,
+
+    /// Only print the hash of the engine configuration of every profile as `<profile> <hash>`, without precompiling
+    /// anything. The benchmark runner compares it with the one recorded in the manifests
+    #[arg(long, conflicts_with = "force")]
+    config_hash: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Target {
+    Pulley32,
+    Pulley32Be,
+    Pulley64,
+}
+
+impl ValueEnum for Target {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Pulley32, Self::Pulley32Be, Self::Pulley64]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            Self::Pulley32 => Some(PossibleValue::new("pulley32")),
+            Self::Pulley32Be => Some(PossibleValue::new("pulley32be")),
+            Self::Pulley64 => Some(PossibleValue::new("pulley64")),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error, Diagnostic)]
 enum Error {
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
+    #[error("Precompiling with wasmtime {0} failed ({1})")]
+    #[diagnostic(help("The error reported by the generated script is printed above"))]
+    Precompilation(String, ExitStatus),
+}
+
+/// Script generated from `PRECOMPILING_TEMPLATE`, removed once done even if precompiling failed.
+/// Its path only depends on the version so that cargo can reuse the wasmtime build across runs.
+struct GeneratedScript(PathBuf);
+
+impl GeneratedScript {
+    fn new(version: &str) -> Result<Self, Error> {
+        let dir = env::temp_dir().join("ariel-wasm-precompile");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("precompile_wasmtime_{}.rs", version.replace('.', "_")));
+        fs::write(&path, PRECOMPILING_TEMPLATE.replace("TOCHANGE", version))?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for GeneratedScript {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
 }
 
 fn main() -> miette::Result<()> {
     let args = Args::parse();
-    let version = args.wasmtime_version;
-    // Write the template in a file
-    fs::write("temp.rs", PRECOMPILING_TEMPLATE.as_bytes()).map_err(Error::from)?;
-    // Prepare the expression to subsitute TOCHANGE with the asked version number
-    let substitute_regexp = format!("s/TOCHANGE/{}/g", version);
-    std::process::Command::new("sed").args(
-        ["-i", "-e", substitute_regexp.as_str(), "temp.rs"]
-    ).output().map_err(Error::from)?;
-
-    let std::process::Output {stdout, stderr: _, status: _} = std::process::Command::new("cargo").args(
-        ["+nightly", "-Z", "script", "temp.rs"]
-    ).output().map_err(Error::from)?;
-    std::println!("{}", String::from_utf8_lossy(&stdout));
-    fs::remove_file("temp.rs").map_err(Error::from)?;
+    let target = args.target.to_possible_value().unwrap();
+    let mode = match (args.force, args.config_hash) {
+        (_, true) => "hash",
+        (true, false) => "force",
+        (false, false) => "incremental",
+    };
+
+    for version in args.wasmtime_versions.iter() {
+        let script = GeneratedScript::new(version)?;
+
+        // The target, the mode and the profiles are forwarded as arguments of the generated script
+        let status = Command::new("cargo")
+            .args(["+nightly", "-Z", "script"])
+            .arg(&script.0)
+            .args([target.get_name(), mode])
+            .args(&args.profiles)
+            .status()
+            .map_err(Error::from)?;
+
+        if !status.success() {
+            return Err(Error::Precompilation(version.clone(), status).into());
+        }
+    }
     Ok(())
 }
 
@@ -56,17 +132,28 @@ const PRECOMPILING_TEMPLATE: &str = r####"#!/usr/bin/env -S cargo +nightly -Zscr
 edition = "2024"
 
 [dependencies]
-wasmtime = {version = "=TOCHANGE", default-features = false, features = ["cranelift", "pulley"]}
+wasmtime = {version = "=TOCHANGE", default-features = false, features = ["cranelift", "pulley", "runtime"]}
+toml = "0.8"
 ---
 
-use std::fs;
+use std::{env, fs};
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
 use wasmtime::{Config, Engine, OptLevel};
 
 const WAMSTIME_VERSION: &str ="TOCHANGE";
 
-fn main() {
+/// Payload profiles and the options they change on top of `base_config`.
+/// Profiles changing runtime checks must be paired with the matching firmware feature.
+const PROFILES: &[(&str, fn(&mut Config))] = &[
+    ("default", |_| {}),
+    ("opt-none", |config| { config.cranelift_opt_level(OptLevel::None); }),
+    ("opt-speed-and-size", |config| { config.cranelift_opt_level(OptLevel::SpeedAndSize); }),
+    // Requires the `wasmtime-fuel` feature
+    ("fuel", |config| { config.consume_fuel(true); }),
+];
 
-    std::println!("Precompiling using wasmtime version {}", WAMSTIME_VERSION);
+fn base_config(target: &str) -> Config {
     let mut config = Config::new();
 
     // Options found to reduce the output code size the most at least for components
@@ -76,7 +163,7 @@ fn main() {
     config.cranelift_opt_level(OptLevel::Speed);
 
     config.wasm_custom_page_sizes(true);
-    config.target("pulley32").unwrap();
+    config.target(target).unwrap();
 
     // 0 means limiting ourselves to what the module asked
     // This needs to be set at pre-compile time and at runtime in the engine
@@ -88,33 +175,121 @@ fn main() {
     // since it can grow by reallocating.
     config.memory_may_move(false);
 
+    // Pulley targets never use signals-based traps, make the explicit bounds checks visible here
+    config.signals_based_traps(false);
 
-    // Create an `Engine` with that configuration.
-    let engine = Engine::new(&config).unwrap();
+    config
+}
 
-    // read the benchmarks dir
+/// FNV-1a, used instead of `DefaultHasher` so that hashes stay comparable across toolchains
+struct Fnv1a(u64);
 
-    for dir in fs::read_dir("benchmarks").unwrap() {
-        for file in fs::read_dir(dir.unwrap().path()).unwrap().filter_map(
-            |entry| {
-                if entry.is_ok() {
-                    let f = entry.unwrap();
-                    match f.path().extension().map(|ext| { ext.to_str() }).flatten() {
-                        Some("wasm" ) => { Some(f) },
-                        _ => None
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let target = args.next().expect("The pulley target must be given as first argument");
+    let mode = args.next().expect("The mode must be given as second argument");
+    let force = mode == "force";
+    let requested = args.collect::<Vec<_>>();
+
+    for name in requested.iter() {
+        if !PROFILES.iter().any(|(profile, _)| profile == name) {
+            panic!("Unknown profile {}", name);
+        }
+    }
+
+    if mode != "hash" {
+        std::println!("Precompiling using wasmtime version {} for {}", WAMSTIME_VERSION, target);
+    }
+
+    for (profile, apply) in PROFILES.iter().filter(|(profile, _)| requested.is_empty() || requested.iter().any(|name| name == profile)) {
+        let mut config = base_config(&target);
+        apply(&mut config);
+
+        // Create an `Engine` with that configuration.
+        let engine = Engine::new(&config).unwrap();
+
+        let mut hasher = Fnv1a::new();
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        let config_hash = format!("{:016x}", hasher.finish());
+        if mode == "hash" {
+            std::println!("{} {}", profile, config_hash);
+            continue;
+        }
+
+        // read the benchmarks dir
+
+        for dir in fs::read_dir("benchmarks").unwrap() {
+            let suite_dir = dir.unwrap().path();
+            let out_dir = suite_dir.join(format!("wasmtime-{}", WAMSTIME_VERSION)).join(&target).join(profile);
+            fs::create_dir_all(&out_dir).unwrap();
+            let manifest_path = out_dir.join("manifest.toml");
+
+            // Source hashes from the previous run, only trusted if it used the same engine configuration
+            let previous_sources = fs::read_to_string(&manifest_path).ok()
+                .and_then(|manifest| manifest.parse::<toml::Table>().ok())
+                .filter(|manifest| !force && manifest.get("config_hash").and_then(|hash| hash.as_str()) == Some(config_hash.as_str()))
+                .and_then(|manifest| manifest.get("sources").and_then(|sources| sources.as_table()).cloned())
+                .unwrap_or_default();
+            let mut sources = BTreeMap::new();
+
+            for file in fs::read_dir(&suite_dir).unwrap().filter_map(
+                |entry| {
+                    if entry.is_ok() {
+                        let f = entry.unwrap();
+                        match f.path().extension().map(|ext| { ext.to_str() }).flatten() {
+                            Some("wasm" ) => { Some(f) },
+                            _ => None
+                        }
+                    } else {
+                        None
                     }
+                }
+            ) {
+                let name = file.path().file_stem().unwrap().to_string_lossy().into_owned();
+                let wasm = fs::read(file.path()).unwrap();
+                let mut hasher = Fnv1a::new();
+                hasher.write(&wasm);
+                let source_hash = format!("{:016x}", hasher.finish());
+
+                let out_path = out_dir.join(file.file_name()).with_extension("cwasm");
+                if out_path.exists() && previous_sources.get(&name).and_then(|hash| hash.as_str()) == Some(source_hash.as_str()) {
+                    std::println!("Skipping {:?} ({} profile), unchanged", file.path(), profile);
                 } else {
-                    None
+                    std::println!("Precompiling {:?} ({} profile)", file.path(), profile);
+                    let precompiled = engine.precompile_module(&wasm)
+                        .unwrap_or_else(|e| panic!("Failed to precompile {:?}: {:?}", file.path(), e));
+                    fs::write(out_path, &precompiled).unwrap();
                 }
+                sources.insert(name, source_hash);
+            }
+
+            // Checked by the benchmark runner before flashing anything
+            let mut manifest = format!(
+                "wasmtime_version = \"{}\"\ntarget = \"{}\"\nprofile = \"{}\"\nconfig_hash = \"{}\"\n\n[sources]\n",
+                WAMSTIME_VERSION, target, profile, config_hash
+            );
+            for (name, hash) in sources {
+                manifest.push_str(&format!("{} = \"{}\"\n", name, hash));
             }
-        ) {
-            std::println!("Precompiling {:?}", file.path());
-            let mut path_copy = file.path().clone();
-            let wasm = fs::read(&path_copy).unwrap();
-            let precompiled = engine.precompile_module(&wasm).unwrap();
-            path_copy.set_extension("cwasm");
-            // std::println!("Writing the precompiled file at {:?}", path_copy);
-            fs::write(path_copy, &precompiled).unwrap();
+            fs::write(manifest_path, manifest).unwrap();
         }
     }
-}"####;
\ No newline at end of file
+}"####;
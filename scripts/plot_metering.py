@@ -0,0 +1,73 @@
+import matplotlib.pyplot as plt
+import numpy as np
+import sys
+
+def read_embench(file_name: str) -> tuple[dict[str, float], dict[str, int]]:
+    # Last time and fuel recorded for each benchmark, the result files are appended to
+    times: dict[str, float] = {}
+    fuel: dict[str, int] = {}
+    with open(file_name) as f:
+        for line in f.readlines():
+            if len(line) == 0 or line[0] == "#":
+                continue
+            splitted: list[str] = [field.strip() for field in line.split(',')]
+            # fuel, benchmark_name, units
+            if splitted[0] == "fuel":
+                fuel[splitted[1]] = int(splitted[2])
+                continue
+            # Benchmarks that didn't produce any result, see run_benchmarks.rs
+            if splitted[1] in ("failed", "timeout", "error"):
+                continue
+            # benchmark_name, score, score_dev, timing, dev
+            assert len(splitted) == 5
+            times[splitted[0]] = float(splitted[3])
+    return (times, fuel)
+
+def plot_metering(paths: list[tuple[str, str, str]]):
+    plt.rcParams.update({'font.size': 30})
+    # Time with metering over time without, for each benchmark and runtime
+    slowdowns: dict[str, dict[str, float]] = {}
+    runtime_names: list[str] = []
+    for (runtime_name, metering_file, baseline_file) in paths:
+        (metered, fuel) = read_embench(metering_file)
+        (baseline, _) = read_embench(baseline_file)
+        print(f"{runtime_name}:")
+        print(f"    {'benchmark':<16} {'slowdown':>8} {'fuel':>12}")
+        for name in sorted(metered.keys() & baseline.keys()):
+            slowdown = metered[name] / baseline[name]
+            print(f"    {name:<16} {slowdown:>8.2f} {fuel.get(name, ''):>12}")
+            # Nbody is a useless benchmark because it gets optimized out
+            if name == "nbody":
+                continue
+            label = "sglib" if name == "sglib-combined" else name
+            try:
+                slowdowns[label][runtime_name] = slowdown
+            except KeyError:
+                slowdowns[label] = { runtime_name: slowdown }
+        runtime_names.append(runtime_name)
+
+    _fig, ax = plt.subplots()
+    slowdowns = dict(sorted(slowdowns.items()))
+    n_runtimes = len(runtime_names)
+    for i, cur_runtime in enumerate(runtime_names):
+        ax.bar(
+            x = np.arange(len(slowdowns)) + i/(n_runtimes + 1),
+            height = [slowdown_of_b.get(cur_runtime, 0) for slowdown_of_b in slowdowns.values()],
+            width = 1./(n_runtimes + 1),
+            label = cur_runtime,
+            tick_label = list(slowdowns.keys()) if i == 0 else None,
+        )
+    ax.axhline(1, color="black", linewidth=1)
+    ax.set_ylabel("Slowdown with metering")
+    ax.tick_params(axis='x', labelrotation=45)
+    ax.legend()
+
+    plt.show()
+
+
+if __name__ == "__main__":
+    # (runtime metering_file baseline_file)*
+    args: list[str] = sys.argv[1:]
+    annotated_files = [(args[i], args[i+1], args[i+2]) for i in range(0, len(args), 3)]
+    print(annotated_files)
+    plot_metering(paths=annotated_files)
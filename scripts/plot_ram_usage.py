@@ -12,6 +12,8 @@ def plot_benchmark(benchmark: str, paths: list[(str, str)], board: str):
                 # benchmark_name, peak heap, .data, .bss
                 if len(line) == 0 or line[0] == "#": continue
                 splitted: list[str] = line.split(',')
+                # Benchmarks that didn't produce any result, see run_benchmarks.rs
+                if splitted[1].strip() in ("failed", "timeout", "error"): continue
                 assert len(splitted) >= 4
                 name: str = splitted[0]
                 if name == "sglib-combined":
@@ -15,6 +15,9 @@ def plot_benchmark(benchmark: str, paths: list[(str, str)], board: str):
                     continue
                 # benchmark_name, score, score_dev, timing, dev
                 splitted: list[str] = line.split(',')
+                # Benchmarks that didn't produce any result, see run_benchmarks.rs
+                if splitted[1].strip() in ("failed", "timeout", "error"):
+                    continue
                 assert len(splitted) == 5
                 name: str = splitted[0]
                 if name == "sglib-combined":
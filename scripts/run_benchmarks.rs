@@ -8,56 +8,125 @@ edition = "2024"
 clap = { version = "4.5.40", features = ["derive"] }
 miette = { version = "7.2", features = ["fancy"] }
 thiserror = { version = "2.0" }
+serde = { version = "1.0", features = ["derive"] }
+toml = "0.8"
+object = { version = "0.36", default-features = false, features = ["read"] }
 ---
 
-use std::{fs, io, path::PathBuf};
+use std::{collections::{BTreeMap, HashMap}, env, fs, io, io::{BufRead, Read, Write}, path::{Path, PathBuf}, sync::mpsc, thread, time::{Duration, Instant}};
 use std::process;
-use clap::{Parser, ValueEnum, builder::PossibleValue};
+use clap::{Parser, ValueEnum, builder::{PossibleValue, PossibleValuesParser}};
 use miette::Diagnostic;
+use object::{Object, ObjectSection};
 
 /// Helper script to run benchmarks and report the results
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Type of benchmark to use
-    #[arg(short, long)]
-    benchmark: Benchmark,
+    /// Types of benchmark to use, comma separated or `all`
+    #[arg(short, long, required = true, value_delimiter = ',', value_parser = selection::<Benchmark>())]
+    benchmark: Vec<String>,
 
-    /// Output file of the benchmark results. If it exists, results will be appended to it
-    #[arg(short = 'o', long = "output-file")]
-    output_file: PathBuf,
+    /// Directory where the results are written, in `<board>/<runtime>.txt`. Existing files are appended to
+    #[arg(long = "results-dir", default_value = "results")]
+    results_dir: PathBuf,
 
-    /// Board to run the benchmarks on
-    #[arg(long = "board")]
-    board: String,
+    /// Boards to run the benchmarks on, comma separated or `all`, see `--boards-file`
+    #[arg(long = "board", required = true, value_delimiter = ',')]
+    board: Vec<String>,
 
-    /// Runtime to evaluate defaults to wasmtime
-    #[arg(short, long)]
-    runtime: Runtime,
+    /// Runtimes to evaluate, comma separated or `all`
+    #[arg(short, long, required = true, value_delimiter = ',', value_parser = selection::<Runtime>())]
+    runtime: Vec<String>,
 
     /// Probe ID used by probe-rs to disambiguate in presence of several devices
     #[arg(short, long)]
     probe: Option<String>,
 
-    /// Provide the arch string required for wamr
-    #[arg(long)]
-    arch: Option<Arch>,
+    /// Registry of the boards this script knows how to build for and read the results of
+    #[arg(long = "boards-file", default_value = "boards.toml")]
+    boards_file: PathBuf,
 
     /// Monitor the Dynamic Memory usage
     #[arg(long = "monitor-heap")]
     monitor: bool,
+
+    /// Also record the time of every Embench run in `samples-<runtime>.txt`
+    #[arg(long = "raw-samples")]
+    raw_samples: bool,
+
+    /// Add the cycle count of every run to the raw samples, Cortex-M boards only
+    #[arg(long = "cycle-count")]
+    cycle_count: bool,
+
+    /// Run Embench with fuel metering and log the fuel consumed by a run, in `metering-<runtime>.txt`
+    #[arg(long, conflicts_with = "monitor")]
+    metering: bool,
+
+    /// Seconds after which building, flashing and running a single benchmark is aborted
+    #[arg(long, default_value_t = 600)]
+    timeout: u64,
+
+    /// How many more times a failed or timed out benchmark is tried
+    #[arg(long, default_value_t = 1)]
+    retries: u32,
+
+    /// File where the output of failed attempts is appended, defaults to `failures.log` in the results directory
+    #[arg(long = "failure-log")]
+    failure_log: Option<PathBuf>,
+
+    /// Write the commands of every combination to this shell script instead of running them
+    #[arg(long = "dry-run", conflicts_with = "build_only")]
+    dry_run: Option<PathBuf>,
+
+    /// Only build every combination, without flashing any board
+    #[arg(long = "build-only")]
+    build_only: bool,
+}
+
+/// Accepts the values of `T` as well as `all`
+fn selection<T: ValueEnum>() -> PossibleValuesParser {
+    PossibleValuesParser::new(
+        T::value_variants()
+            .iter()
+            .filter_map(ValueEnum::to_possible_value)
+            .chain([PossibleValue::new("all")]),
+    )
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Resolves the values given for one axis of the sweep, without duplicates
+fn select<T: ValueEnum + Copy + PartialEq>(names: &[String]) -> Vec<T> {
+    if names.iter().any(|name| name == "all") {
+        return T::value_variants().to_vec();
+    }
+    let mut selected = Vec::new();
+    for name in names {
+        // Already validated by `selection`
+        let value = T::from_str(name, false).unwrap();
+        if !selected.contains(&value) {
+            selected.push(value);
+        }
+    }
+    selected
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum Benchmark {
     Embench1,
     Embench2,
     CoreMark,
+    HostCalls,
+    LinearMemory,
+    MemoryGrowth,
+    Preemption,
+    AsyncHost,
+    MultiInstance,
+    Wasi,
 }
 
 impl ValueEnum for Benchmark {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Self::Embench1, Self::Embench2, Self::CoreMark]
+        &[Self::Embench1, Self::Embench2, Self::CoreMark, Self::HostCalls, Self::LinearMemory, Self::MemoryGrowth, Self::Preemption, Self::AsyncHost, Self::MultiInstance, Self::Wasi]
     }
 
     fn to_possible_value(&self) -> Option<PossibleValue> {
@@ -65,21 +134,36 @@ impl ValueEnum for Benchmark {
             Self::Embench1 => Some(PossibleValue::new("embench-1")),
             Self::Embench2 => Some(PossibleValue::new("embench-2")),
             Self::CoreMark => Some(PossibleValue::new("coremark")),
+            Self::HostCalls => Some(PossibleValue::new("host-calls")),
+            Self::LinearMemory => Some(PossibleValue::new("linear-memory")),
+            Self::MemoryGrowth => Some(PossibleValue::new("memory-growth")),
+            Self::Preemption => Some(PossibleValue::new("preemption")),
+            Self::AsyncHost => Some(PossibleValue::new("async-host")),
+            Self::MultiInstance => Some(PossibleValue::new("multi-instance")),
+            Self::Wasi => Some(PossibleValue::new("wasi")),
         }
     }
 }
 
 impl Benchmark {
-    fn to_dirname(&self) -> &str {
+    /// `None` for the suites that aren't supported yet
+    fn to_dirname(&self) -> Option<&str> {
         match self {
-            Self::Embench1 => "embench-1.0",
-            Self::Embench2 => unimplemented!("This benchmark suite isn't yet supported"),
-            Self::CoreMark => "coremark",
+            Self::Embench1 => Some("embench-1.0"),
+            Self::Embench2 => None,
+            Self::CoreMark => Some("coremark"),
+            Self::HostCalls => Some("host-calls"),
+            Self::LinearMemory => Some("linear-memory"),
+            Self::MemoryGrowth => Some("memory-growth"),
+            Self::Preemption => Some("preemption"),
+            Self::AsyncHost => Some("async-host"),
+            Self::MultiInstance => Some("multi-instance"),
+            Self::Wasi => Some("wasi"),
         }
     }
 
     // It's the same but separating
-    fn to_laze_module(&self) -> &str {
+    fn to_laze_module(&self) -> Option<&str> {
         self.to_dirname()
     }
 }
@@ -88,7 +172,17 @@ impl Benchmark {
 enum Runtime {
     Wasmtime,
     WasmtimeNoSIMD,
+    WasmtimeFuel,
+    WasmtimeStack16K,
+    WasmtimeGrowth64K,
+    WasmtimeOptNone,
+    WasmtimeOptSpeedAndSize,
     Wasmi,
+    WasmiEager,
+    WasmiLazy,
+    WasmiChecked,
+    WasmiFuel,
+    WasmiNoFloats,
     WasmInterpreter,
     WasefireInterpreter,
     WamrFast,
@@ -101,7 +195,17 @@ impl ValueEnum for Runtime {
         &[
             Self::Wasmtime,
             Self::WasmtimeNoSIMD,
+            Self::WasmtimeFuel,
+            Self::WasmtimeStack16K,
+            Self::WasmtimeGrowth64K,
+            Self::WasmtimeOptNone,
+            Self::WasmtimeOptSpeedAndSize,
             Self::Wasmi,
+            Self::WasmiEager,
+            Self::WasmiLazy,
+            Self::WasmiChecked,
+            Self::WasmiFuel,
+            Self::WasmiNoFloats,
             Self::WasmInterpreter,
             Self::WasefireInterpreter,
             Self::WamrFast,
@@ -114,7 +218,17 @@ impl ValueEnum for Runtime {
         match self {
             Self::Wasmtime => Some(PossibleValue::new("wasmtime")),
             Self::WasmtimeNoSIMD => Some(PossibleValue::new("wasmtime-no-simd")),
+            Self::WasmtimeFuel => Some(PossibleValue::new("wasmtime-fuel")),
+            Self::WasmtimeStack16K => Some(PossibleValue::new("wasmtime-stack-16k")),
+            Self::WasmtimeGrowth64K => Some(PossibleValue::new("wasmtime-growth-64k")),
+            Self::WasmtimeOptNone => Some(PossibleValue::new("wasmtime-opt-none")),
+            Self::WasmtimeOptSpeedAndSize => Some(PossibleValue::new("wasmtime-opt-speed-and-size")),
             Self::Wasmi => Some(PossibleValue::new("wasmi")),
+            Self::WasmiEager => Some(PossibleValue::new("wasmi-eager")),
+            Self::WasmiLazy => Some(PossibleValue::new("wasmi-lazy")),
+            Self::WasmiChecked => Some(PossibleValue::new("wasmi-checked")),
+            Self::WasmiFuel => Some(PossibleValue::new("wasmi-fuel")),
+            Self::WasmiNoFloats => Some(PossibleValue::new("wasmi-no-floats")),
             Self::WasmInterpreter => Some(PossibleValue::new("wasm-interpreter")),
             Self::WasefireInterpreter => Some(PossibleValue::new("wasefire")),
             Self::WamrFast => Some(PossibleValue::new("wamr-fast")),
@@ -127,7 +241,9 @@ impl ValueEnum for Runtime {
 impl Runtime {
     fn payload_extension(&self) -> &str {
         match self {
-            Self::Wasmtime | Self::WasmtimeNoSIMD => {
+            Self::Wasmtime | Self::WasmtimeNoSIMD | Self::WasmtimeFuel
+                | Self::WasmtimeStack16K | Self::WasmtimeGrowth64K
+                | Self::WasmtimeOptNone | Self::WasmtimeOptSpeedAndSize => {
                 "cwasm"
             },
             Self::WamrAOT => {
@@ -139,21 +255,84 @@ impl Runtime {
         }
     }
 
-    fn to_laze_module(&self) -> &str {
+    /// Profile used by `precompile_higher.rs` to produce the payload, wasmtime only
+    fn wasmtime_profile(&self) -> Option<&'static str> {
+        match self {
+            Self::Wasmtime | Self::WasmtimeNoSIMD | Self::WasmtimeStack16K | Self::WasmtimeGrowth64K => Some("default"),
+            Self::WasmtimeFuel => Some("fuel"),
+            Self::WasmtimeOptNone => Some("opt-none"),
+            Self::WasmtimeOptSpeedAndSize => Some("opt-speed-and-size"),
+            _ => None,
+        }
+    }
+
+    /// Cranelift optimization level of the payload profile
+    fn wasmtime_opt_level(&self) -> Option<&str> {
+        match self.wasmtime_profile()? {
+            "opt-none" => Some("none"),
+            "opt-speed-and-size" => Some("speed_and_size"),
+            _ => Some("speed"),
+        }
+    }
+
+    /// Crate providing the runtime, whose version is recorded in the results
+    fn crate_name(&self) -> &str {
         match self {
-            Self::Wasmtime => "wasmtime",
+            Self::Wasmtime | Self::WasmtimeNoSIMD | Self::WasmtimeFuel
+                | Self::WasmtimeStack16K | Self::WasmtimeGrowth64K
+                | Self::WasmtimeOptNone | Self::WasmtimeOptSpeedAndSize => "wasmtime",
+            Self::Wasmi | Self::WasmiEager | Self::WasmiLazy | Self::WasmiChecked
+                | Self::WasmiFuel | Self::WasmiNoFloats => "wasmi",
+            Self::WasmInterpreter => "wasm-interpreter",
+            Self::WasefireInterpreter => "wasefire-interpreter",
+            Self::WamrFast | Self::WamrAOT | Self::Wamr => "wamr-rust-sdk",
+        }
+    }
+
+    /// Directory containing the payloads of a benchmark suite for this runtime
+    fn payload_dir(&self, suite_dir: &Path, wasmtime_version: &str, arch: Arch) -> PathBuf {
+        match self.wasmtime_profile() {
+            Some(profile) => suite_dir
+                .join(format!("wasmtime-{}", wasmtime_version))
+                .join(arch.pulley_target())
+                .join(profile),
+            None => suite_dir.to_owned(),
+        }
+    }
+
+    /// Whether the firmware can run this runtime with fuel metering. Fuel is compiled into the wasmtime payloads,
+    /// only the `fuel` profile has it
+    fn can_meter(&self) -> bool {
+        self.wasmtime_profile().is_none_or(|profile| profile == "fuel")
+    }
+
+    /// `None` for the runtimes that aren't supported yet
+    fn to_laze_module(&self) -> Option<&str> {
+        let module = match self {
+            // Changing the optimization level only changes the payload
+            Self::Wasmtime | Self::WasmtimeOptNone | Self::WasmtimeOptSpeedAndSize => "wasmtime",
             Self::WasmtimeNoSIMD => "wasmtime-no-simd",
+            Self::WasmtimeFuel => "wasmtime-fuel",
+            Self::WasmtimeStack16K => "wasmtime-stack-16k",
+            Self::WasmtimeGrowth64K => "wasmtime-growth-64k",
             Self::Wasmi => "wasmi",
+            Self::WasmiEager => "wasmi-eager",
+            Self::WasmiLazy => "wasmi-lazy",
+            Self::WasmiChecked => "wasmi-checked",
+            Self::WasmiFuel => "wasmi-fuel",
+            Self::WasmiNoFloats => "wasmi-no-floats",
             Self::WasmInterpreter => "wasm-interpreter",
             Self::WasefireInterpreter => "wasefire",
             Self::WamrFast => "wamr-fast",
-            Self::WamrAOT => unimplemented!("This runtime isn't yet supported"),
+            Self::WamrAOT => return None,
             Self::Wamr => "wamr",
-        }
+        };
+        Some(module)
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum Arch {
     ThumbV7,
     ThumbV8,
@@ -161,164 +340,1248 @@ enum Arch {
     RiscV32,
 }
 
-impl ValueEnum for Arch {
-    fn value_variants<'a>() -> &'a [Self] {
-        &[Self::ThumbV7, Self::ThumbV8, Self::Xtensa, Self::RiscV32]
-    }
-
-    fn to_possible_value(&self) -> Option<PossibleValue> {
+impl Arch {
+    /// Pulley target the wasmtime payloads must be precompiled for. Every board is 32-bit little endian
+    fn pulley_target(&self) -> &'static str {
         match self {
-            Self::ThumbV7 => Some(PossibleValue::new("thumbv7")),
-            Self::ThumbV8 => Some(PossibleValue::new("thumbv8")),
-            Self::Xtensa => Some(PossibleValue::new("xtensa")),
-            Self::RiscV32 => Some(PossibleValue::new("riscv32")),
+            Self::ThumbV7 | Self::ThumbV8 | Self::Xtensa | Self::RiscV32 => "pulley32",
         }
     }
 }
 
-impl Arch {
-    fn to_wamr_build_target(&self) -> &str {
-        match self {
-            Self::ThumbV7 => "THUMBV7",
-            Self::ThumbV8 => "THUMBV8.MAIN",
-            Self::Xtensa => "XTENSA",
-            Self::RiscV32 => "RISCV32",
+/// How the logs of the firmware reach this script
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LogTransport {
+    /// probe-rs writes the defmt logs received over RTT to `--target-output-file`
+    Rtt,
+    /// The espflash monitor prints the serial output of the board, and never exits by itself
+    Serial,
+}
+
+/// An entry of the board registry, `boards.toml` documents every field
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Board {
+    name: String,
+    arch: Arch,
+    results_dir: String,
+    log_transport: LogTransport,
+    wamr_build_target: String,
+    target_cflags: Option<String>,
+    target_cc: Option<String>,
+    heap_size: Option<u32>,
+    wasefire_memory_budget: Option<u32>,
+    runtimes: Option<Vec<String>>,
+}
+
+impl Board {
+    fn supports(&self, runtime: Runtime) -> bool {
+        self.runtimes.as_ref().is_none_or(|runtimes| runtimes.contains(&value_name(&runtime)))
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BoardRegistry {
+    board: Vec<Board>,
+}
+
+impl BoardRegistry {
+    fn read(path: &Path) -> Result<Self, Error> {
+        let registry: Self = toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| Error::Toml(path.to_owned(), e))?;
+        for board in &registry.board {
+            for runtime in board.runtimes.iter().flatten() {
+                if Runtime::from_str(runtime, false).is_err() {
+                    return Err(Error::UnknownRuntime(path.to_owned(), board.name.clone(), runtime.clone()));
+                }
+            }
         }
+        Ok(registry)
     }
 
-    fn from_board_name(board: &str) -> Self {
-        match board {
-            "nrf52840dk" => Self::ThumbV7,
-            "rpi-pico2-w" => Self::ThumbV8,
-            "espressif-esp32-devkitc" => Self::Xtensa,
-            "espressif-esp32c6-devkit" => Self::RiscV32,
-            "dfrobot-firebeetle2-esp32-c6" => Self::RiscV32,
-            _ => panic!("This board isn't recognized. Update this script or explicitly specify the architecture."),
+    /// Resolves the boards given on the command line, `all` selects every board of the registry
+    fn select(&self, names: &[String], path: &Path) -> Result<Vec<Board>, Error> {
+        if names.iter().any(|name| name == "all") {
+            return Ok(self.board.clone());
+        }
+        let mut boards: Vec<Board> = Vec::new();
+        for name in names {
+            let board = self.board.iter().find(|board| &board.name == name)
+                .ok_or_else(|| Error::UnknownBoard(name.clone(), path.to_owned()))?;
+            if !boards.iter().any(|selected| selected.name == board.name) {
+                boards.push(board.clone());
+            }
         }
+        Ok(boards)
     }
 }
 
+/// Written by `precompile_higher.rs` next to the wasmtime payloads
+#[derive(Debug, serde::Deserialize)]
+struct PayloadManifest {
+    wasmtime_version: String,
+    target: String,
+    profile: String,
+    /// `Engine::precompile_compatibility_hash` of the engine configuration the payloads were precompiled with
+    config_hash: String,
+}
+
 #[derive(Debug, thiserror::Error, Diagnostic)]
 enum Error {
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
+    #[error("Failed to parse {0}: {1}")]
+    Toml(PathBuf, toml::de::Error),
+    #[error("The board {0} isn't in {1}")]
+    #[diagnostic(help("Add an entry for it to the board registry"))]
+    UnknownBoard(String, PathBuf),
+    #[error("{0} lists an unknown runtime for {1}: {2}")]
+    UnknownRuntime(PathBuf, String, String),
+    #[error("Cargo.toml doesn't pin a wasmtime version")]
+    NoWasmtimeVersion,
+    #[error("{0} isn't a wasmtime precompiled module: {1}")]
+    InvalidCwasm(PathBuf, String),
+    #[error("{path} was precompiled with {field} {found} but the firmware expects {expected}")]
+    #[diagnostic(help("Run `./precompile_higher.rs -w {wasmtime_version}` again"))]
+    CwasmMismatch {
+        path: PathBuf,
+        field: &'static str,
+        found: String,
+        expected: String,
+        wasmtime_version: String,
+    },
+    #[error("No precompiled payloads found in {0}")]
+    #[diagnostic(help("Run `./precompile_higher.rs -w {1}` first"))]
+    MissingPayloads(PathBuf, String),
+    #[error("The payloads in {dir} were precompiled with {field} {found:?} but the firmware expects {expected:?}")]
+    #[diagnostic(help("Run `./precompile_higher.rs -w {wasmtime_version}` again"))]
+    PayloadMismatch {
+        dir: PathBuf,
+        field: &'static str,
+        found: String,
+        expected: String,
+        wasmtime_version: String,
+    },
+    #[error("Failed to hash the engine configuration of wasmtime {0} for the {1} profile")]
+    #[diagnostic(help("The error reported by `precompile_higher.rs` is printed above"))]
+    ConfigHash(String, String),
 }
 
-fn main() -> miette::Result<()> {
-    let args = Args::parse();
-    let runtime = args.runtime;
-    let benchmark = args.benchmark;
-    let dir_path = format!("benchmarks/{}", benchmark.to_dirname());
-    let board = args.board;
-    let output_file = args.output_file;
-    let probe = args.probe.unwrap_or_default();
-    let monitor_heap = args.monitor;
-    let arch = if let Some(arch) = args.arch {
-        arch
+/// Wasmtime major release whose `Tunables` layout `CwasmHeader::parse` knows about
+const TUNABLES_LAYOUT_VERSION: &str = "42";
+
+/// Engine metadata wasmtime embeds in the `.wasmtime.engine` section of every `.cwasm`
+#[derive(Debug)]
+struct CwasmHeader {
+    /// Only the major version is recorded, and checked by wasmtime when loading the module
+    wasmtime_version: String,
+    target: String,
+    opt_level: Option<String>,
+    tunables: Option<CwasmTunables>,
+}
+
+/// The subset of wasmtime's `Tunables` that the firmware engine configuration sets
+#[derive(Debug)]
+struct CwasmTunables {
+    memory_reservation: u64,
+    consume_fuel: bool,
+    epoch_interruption: bool,
+    memory_may_move: bool,
+    table_lazy_init: bool,
+    signals_based_traps: bool,
+    memory_init_cow: bool,
+}
+
+impl CwasmTunables {
+    /// What `engine_config` in `src/wasmtime.rs` sets up for this runtime
+    fn firmware(runtime: Runtime) -> Self {
+        Self {
+            memory_reservation: 0,
+            consume_fuel: runtime == Runtime::WasmtimeFuel,
+            // Epochs need 64-bit atomics, which none of the boards have
+            epoch_interruption: false,
+            memory_may_move: false,
+            table_lazy_init: false,
+            signals_based_traps: false,
+            memory_init_cow: false,
+        }
+    }
+
+    /// First option whose value differs from `expected`, as (name, found, expected)
+    fn difference(&self, expected: &Self) -> Option<(&'static str, String, String)> {
+        [
+            ("memory_reservation", self.memory_reservation.to_string(), expected.memory_reservation.to_string()),
+            ("consume_fuel", self.consume_fuel.to_string(), expected.consume_fuel.to_string()),
+            ("epoch_interruption", self.epoch_interruption.to_string(), expected.epoch_interruption.to_string()),
+            ("memory_may_move", self.memory_may_move.to_string(), expected.memory_may_move.to_string()),
+            ("table_lazy_init", self.table_lazy_init.to_string(), expected.table_lazy_init.to_string()),
+            ("signals_based_traps", self.signals_based_traps.to_string(), expected.signals_based_traps.to_string()),
+            ("memory_init_cow", self.memory_init_cow.to_string(), expected.memory_init_cow.to_string()),
+        ].into_iter().find(|(_, found, expected)| found != expected)
+    }
+}
+
+/// Minimal reader for the postcard encoding used by wasmtime for the engine metadata
+struct Postcard<'a>(&'a [u8]);
+
+impl<'a> Postcard<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let (first, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(*first)
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0_u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.byte()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn str(&mut self) -> Option<&'a str> {
+        let len = self.varint()? as usize;
+        if self.0.len() < len {
+            return None;
+        }
+        let (string, rest) = self.0.split_at(len);
+        self.0 = rest;
+        std::str::from_utf8(string).ok()
+    }
+
+    /// Reads a `Vec<(&str, FlagValue)>` and returns the value of `name` if it's present
+    fn flags(&mut self, name: &str) -> Option<Option<String>> {
+        let mut found = None;
+        for _ in 0..self.varint()? {
+            let flag = self.str()?;
+            let value = match self.varint()? {
+                0 => self.str()?.to_owned(),
+                1 => self.byte()?.to_string(),
+                2 => self.bool()?.to_string(),
+                _ => return None,
+            };
+            if flag == name {
+                found = Some(value);
+            }
+        }
+        Some(found)
+    }
+}
+
+impl CwasmHeader {
+    fn read(path: &Path) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidCwasm(path.to_owned(), reason.to_owned());
+        let bytes = fs::read(path)?;
+        let file = object::File::parse(bytes.as_slice()).map_err(|_| invalid("not an ELF file"))?;
+        let section = file.section_by_name(".wasmtime.engine").ok_or_else(|| invalid("no `.wasmtime.engine` section"))?;
+        let data = section.data().map_err(|_| invalid("unreadable `.wasmtime.engine` section"))?;
+        Self::parse(data).ok_or_else(|| invalid("malformed `.wasmtime.engine` section"))
+    }
+
+    /// A version byte, the length prefixed wasmtime version then postcard-encoded metadata
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Postcard(data);
+        if reader.byte()? != 0 {
+            return None;
+        }
+        let len = reader.byte()? as usize;
+        let version = std::str::from_utf8(reader.0.get(..len)?).ok()?.to_owned();
+        reader.0 = &reader.0[len..];
+
+        let target = reader.str()?.to_owned();
+        let opt_level = reader.flags("opt_level")?;
+        reader.flags("")?;
+
+        // The layout of `Tunables` changes between releases, only decode the one we know
+        let tunables = if version == TUNABLES_LAYOUT_VERSION {
+            // collector: Option<Collector>
+            if reader.bool()? {
+                reader.varint()?;
+            }
+            let memory_reservation = reader.varint()?;
+            // memory_guard_size, memory_reservation_for_growth
+            reader.varint()?;
+            reader.varint()?;
+            // debug_native, debug_guest, parse_wasm_debuginfo
+            for _ in 0..3 {
+                reader.bool()?;
+            }
+            let consume_fuel = reader.bool()?;
+            let epoch_interruption = reader.bool()?;
+            let memory_may_move = reader.bool()?;
+            // guard_before_linear_memory
+            reader.bool()?;
+            let table_lazy_init = reader.bool()?;
+            // generate_address_map, debug_adapter_modules, relaxed_simd_deterministic, winch_callable
+            for _ in 0..4 {
+                reader.bool()?;
+            }
+            let signals_based_traps = reader.bool()?;
+            let memory_init_cow = reader.bool()?;
+            Some(CwasmTunables {
+                memory_reservation,
+                consume_fuel,
+                epoch_interruption,
+                memory_may_move,
+                table_lazy_init,
+                signals_based_traps,
+                memory_init_cow,
+            })
+        } else {
+            None
+        };
+
+        Some(Self { wasmtime_version: version, target, opt_level, tunables })
+    }
+}
+
+/// Refuses payloads that the firmware built for `runtime` would fail to deserialize
+fn check_cwasm(path: &Path, runtime: Runtime, arch: Arch, wasmtime_version: &str) -> Result<(), Error> {
+    let header = CwasmHeader::read(path)?;
+    let mismatch = |field: &'static str, found: String, expected: String| Error::CwasmMismatch {
+        path: path.to_owned(),
+        field,
+        found,
+        expected,
+        wasmtime_version: wasmtime_version.to_owned(),
+    };
+
+    let major_version = wasmtime_version.split('.').next().unwrap_or_default();
+    if header.wasmtime_version != major_version {
+        return Err(mismatch("wasmtime major version", header.wasmtime_version, major_version.to_owned()));
+    }
+    let pulley_target = header.target.split('-').next().unwrap_or_default();
+    if pulley_target != arch.pulley_target() {
+        return Err(mismatch("target", header.target.clone(), arch.pulley_target().to_owned()));
+    }
+    let expected_opt_level = runtime.wasmtime_opt_level().map(str::to_owned);
+    if header.opt_level != expected_opt_level {
+        return Err(mismatch("optimization level", format!("{:?}", header.opt_level), format!("{:?}", expected_opt_level)));
+    }
+    match header.tunables {
+        Some(tunables) => match tunables.difference(&CwasmTunables::firmware(runtime)) {
+            Some((field, found, expected)) => Err(mismatch(field, found, expected)),
+            None => Ok(()),
+        },
+        None => {
+            println!("Warning: not checking the engine configuration of {:?}, update `CwasmHeader::parse` for wasmtime {}", path, wasmtime_version);
+            Ok(())
+        }
+    }
+}
+
+/// Version of wasmtime the firmware is built with, as pinned in `Cargo.toml`
+fn firmware_wasmtime_version() -> Result<String, Error> {
+    let manifest_path = PathBuf::from("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)?
+        .parse::<toml::Table>()
+        .map_err(|e| Error::Toml(manifest_path, e))?;
+    manifest.get("dependencies")
+        .and_then(|deps| deps.get("wasmtime"))
+        .and_then(|wasmtime| wasmtime.get("version"))
+        .and_then(|version| version.as_str())
+        .map(|version| version.trim_start_matches('=').to_owned())
+        .ok_or(Error::NoWasmtimeVersion)
+}
+
+/// Hash of the engine configuration `precompile_higher.rs` uses for `profile`, which mirrors the firmware's
+fn firmware_config_hash(wasmtime_version: &str, arch: Arch, profile: &str) -> Result<String, Error> {
+    let output = process::Command::new("cargo")
+        .args(["+nightly", "-Zscript", "-q", "precompile_higher.rs", "--config-hash"])
+        .args(["-w", wasmtime_version, "--target", arch.pulley_target(), "--profile", profile])
+        // Building wasmtime the first time takes a while, its progress is shown
+        .stderr(process::Stdio::inherit())
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines()
+        .filter(|_| output.status.success())
+        .find_map(|line| line.strip_prefix(profile)?.strip_prefix(' '))
+        .map(str::to_owned)
+        .ok_or_else(|| Error::ConfigHash(wasmtime_version.to_owned(), profile.to_owned()))
+}
+
+/// Makes sure the wasmtime payloads in `dir` can be loaded by the firmware before flashing anything
+fn check_payload_manifest(
+    dir: &Path,
+    runtime: Runtime,
+    arch: Arch,
+    wasmtime_version: &str,
+    config_hash: &str,
+) -> Result<(), Error> {
+    let manifest_path = dir.join("manifest.toml");
+    if !manifest_path.exists() {
+        return Err(Error::MissingPayloads(dir.to_owned(), wasmtime_version.to_owned()));
+    }
+    let manifest: PayloadManifest = toml::from_str(&fs::read_to_string(&manifest_path)?)
+        .map_err(|e| Error::Toml(manifest_path, e))?;
+
+    let expected = [
+        ("wasmtime version", manifest.wasmtime_version, wasmtime_version),
+        ("target", manifest.target, arch.pulley_target()),
+        ("profile", manifest.profile, runtime.wasmtime_profile().unwrap_or_default()),
+        ("engine configuration hash", manifest.config_hash, config_hash),
+    ];
+    for (field, found, expected) in expected {
+        if found != expected {
+            return Err(Error::PayloadMismatch {
+                dir: dir.to_owned(),
+                field,
+                found,
+                expected: expected.to_owned(),
+                wasmtime_version: wasmtime_version.to_owned(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// `benchmarks/<suite>/payload.toml`, how the `.wasm` payloads were produced
+#[derive(Debug, serde::Deserialize)]
+struct PayloadInfo {
+    source: String,
+    emscripten: Option<String>,
+    wasm_tools: Option<String>,
+    /// Toolchain of the payloads compiled from Rust
+    rustc: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CargoLock {
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+}
+
+/// Trimmed standard output of a command, if it succeeded
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = process::Command::new(program).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// How the results are produced, recorded in the header of the result files
+struct Environment {
+    commit: String,
+    rustc: String,
+    wamr_commit: String,
+    lock: Vec<LockedPackage>,
+}
+
+impl Environment {
+    fn collect() -> Result<Self, Error> {
+        let commit = command_output("git", &["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_owned());
+        // The results written by this script don't count
+        let dirty = command_output("git", &["status", "--porcelain", "--untracked-files=no", "--", ".", ":!results"])
+            .is_some_and(|status| !status.is_empty());
+        let lock = match fs::read_to_string("Cargo.lock") {
+            Ok(lock) => toml::from_str::<CargoLock>(&lock).map_err(|e| Error::Toml("Cargo.lock".into(), e))?.package,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error.into()),
+        };
+        Ok(Self {
+            commit: if dirty { format!("{commit} (dirty)") } else { commit },
+            rustc: command_output("rustc", &["-V"]).unwrap_or_else(|| "unknown".to_owned()),
+            wamr_commit: command_output("git", &["-C", "third_party/wamr-rust-sdk", "rev-parse", "HEAD"])
+                .unwrap_or_else(|| "unknown".to_owned()),
+            lock,
+        })
+    }
+
+    /// Header lines of the results of `runtime` on `board` with the payloads of `payload_dir`
+    fn header(&self, board: &Board, runtime: Runtime, suite_dir: &Path, payload_dir: &Path) -> Result<Vec<String>, Error> {
+        let mut header = vec![
+            format!("# commit, {}", self.commit),
+            format!("# rustc, {}", self.rustc),
+            format!("# board, {}", board.name),
+        ];
+
+        let name = runtime.crate_name();
+        header.push(match self.lock.iter().find(|package| package.name == name) {
+            Some(LockedPackage { version, source: Some(source), .. }) if !source.starts_with("registry+") => {
+                format!("# {name}, {version} ({source})")
+            }
+            Some(package) => format!("# {name}, {}", package.version),
+            None => format!("# {name}, unknown"),
+        });
+        if let Runtime::Wamr | Runtime::WamrAOT | Runtime::WamrFast = runtime {
+            header.push(format!("# wamr-rust-sdk commit, {}", self.wamr_commit));
+        }
+
+        header.push(format!("# payloads, {}", payload_dir.display()));
+        let info_path = suite_dir.join("payload.toml");
+        if info_path.exists() {
+            let info: PayloadInfo = toml::from_str(&fs::read_to_string(&info_path)?)
+                .map_err(|e| Error::Toml(info_path, e))?;
+            header.push(format!("# payload source, {}", info.source));
+            if let Some(emscripten) = info.emscripten {
+                header.push(format!("# emscripten, {emscripten}"));
+            }
+            if let Some(wasm_tools) = info.wasm_tools {
+                header.push(format!("# wasm-tools, {wasm_tools}"));
+            }
+            if let Some(rustc) = info.rustc {
+                header.push(format!("# payload rustc, {rustc}"));
+            }
+        }
+        Ok(header)
+    }
+}
+
+/// Name of a value as given on the command line
+fn value_name<T: ValueEnum>(value: &T) -> String {
+    value.to_possible_value().unwrap().get_name().to_owned()
+}
+
+/// Where the linear memory checksums of every runtime on `board` are written
+fn checksums_file(results_dir: &Path, board: &Board) -> PathBuf {
+    results_dir.join(&board.results_dir).join("checksums.txt")
+}
+
+/// Where the raw samples of `runtime` on `board` are written with `--raw-samples`
+fn samples_file(results_dir: &Path, board: &Board, runtime: Runtime) -> PathBuf {
+    results_dir.join(&board.results_dir).join(format!("samples-{}.txt", value_name(&runtime)))
+}
+
+/// Conventional location of the results of `runtime` on `board`
+fn results_file(
+    results_dir: &Path,
+    board: &Board,
+    runtime: Runtime,
+    benchmark: Benchmark,
+    monitor_heap: bool,
+    metering: bool,
+) -> PathBuf {
+    let dir = results_dir.join(&board.results_dir);
+    let runtime = value_name(&runtime);
+    if metering {
+        return dir.join(format!("metering-{runtime}.txt"));
+    }
+    match (benchmark, monitor_heap) {
+        (_, true) => dir.join(format!("peakRAM-{runtime}.txt")),
+        // Shared by every runtime, see `append_results`
+        (Benchmark::CoreMark, false) => dir.join("coremark.txt"),
+        (Benchmark::HostCalls, false) => dir.join(format!("host-calls-{runtime}.txt")),
+        (Benchmark::LinearMemory, false) => dir.join(format!("linear-memory-{runtime}.txt")),
+        (Benchmark::MemoryGrowth, false) => dir.join(format!("memory-growth-{runtime}.txt")),
+        (Benchmark::Preemption, false) => dir.join(format!("preemption-{runtime}.txt")),
+        (Benchmark::AsyncHost, false) => dir.join(format!("async-host-{runtime}.txt")),
+        (Benchmark::MultiInstance, false) => dir.join(format!("multi-instance-{runtime}.txt")),
+        (Benchmark::Wasi, false) => dir.join(format!("wasi-{runtime}.txt")),
+        (_, false) => dir.join(format!("{runtime}.txt")),
+    }
+}
+
+#[derive(Debug)]
+enum Outcome {
+    Success,
+    /// Written to the `--dry-run` script
+    Planned,
+    Failed(String),
+    /// The firmware ran, but logged why the benchmark couldn't produce its result
+    Errored(String),
+    Timeout,
+    Skipped(String),
+}
+
+impl Outcome {
+    /// Written in place of the results of a benchmark that didn't produce any, so that gaps are never silent
+    fn marker(&self) -> Option<&str> {
+        match self {
+            Self::Failed(_) => Some("failed"),
+            Self::Timeout => Some("timeout"),
+            Self::Success | Self::Planned | Self::Errored(_) | Self::Skipped(_) => None,
+        }
+    }
+}
+
+/// Quotes `word` for a POSIX shell when needed
+fn shell_quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c)) {
+        word.to_owned()
     } else {
-        Arch::from_board_name(&board)
+        format!("'{}'", word.replace('\'', r#"'\''"#))
+    }
+}
+
+/// A `laze` command along with the environment it needs
+struct Invocation {
+    envs: Vec<(&'static str, String)>,
+    args: Vec<String>,
+}
+
+impl Invocation {
+    fn command(&self) -> process::Command {
+        let mut command = process::Command::new("laze");
+        command.envs(self.envs.iter().map(|(key, value)| (key, value))).args(&self.args);
+        command
+    }
+
+    /// laze modules selected with `-s`
+    fn modules(&self) -> Vec<&str> {
+        self.args.windows(2).filter(|pair| pair[0] == "-s").map(|pair| pair[1].as_str()).collect()
+    }
+
+    /// The same command, to be pasted in a shell
+    fn command_line(&self) -> String {
+        self.envs.iter()
+            .map(|(key, value)| format!("{key}={}", shell_quote(value)))
+            .chain(["laze".to_owned()])
+            .chain(self.args.iter().map(|arg| shell_quote(arg)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// How a `laze` invocation ended
+enum Exit {
+    Status(process::ExitStatus),
+    /// Killed because it outlived the timeout
+    Timeout,
+    /// Killed because everything expected from it was received
+    Stopped,
+}
+
+/// Runs `command`, handing every line it prints to `done`, until it exits by itself, `done` returns true or `timeout`
+/// expires. In the last two cases it's killed along with everything it spawned
+fn run_with_timeout(
+    mut command: process::Command,
+    timeout: Duration,
+    mut done: impl FnMut(&str) -> bool,
+) -> Result<(Exit, Vec<u8>, Vec<u8>), Error> {
+    command.stdout(process::Stdio::piped()).stderr(process::Stdio::piped());
+    // laze runs probe-rs or espflash as children, they have to be killed too
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command.spawn()?;
+
+    // Both pipes are drained concurrently so that a chatty child can't fill them and block
+    let (lines, received) = mpsc::channel();
+    let mut stdout_pipe = io::BufReader::new(child.stdout.take().unwrap());
+    let stdout_thread = thread::spawn(move || {
+        let mut line = Vec::new();
+        while matches!(stdout_pipe.read_until(b'\n', &mut line), Ok(1..)) {
+            if lines.send(std::mem::take(&mut line)).is_err() {
+                break;
+            }
+        }
+    });
+    let mut stderr_pipe = child.stderr.take().unwrap();
+    let stderr_thread = thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = stderr_pipe.read_to_end(&mut buffer);
+        buffer
+    });
+
+    let deadline = Instant::now() + timeout;
+    let mut stdout = Vec::new();
+    let exit = loop {
+        let mut finished = false;
+        while let Ok(line) = received.try_recv() {
+            finished |= done(&String::from_utf8_lossy(&line));
+            stdout.extend(line);
+        }
+        if finished {
+            break Exit::Stopped;
+        }
+        if let Some(status) = child.try_wait()? {
+            break Exit::Status(status);
+        }
+        if Instant::now() >= deadline {
+            break Exit::Timeout;
+        }
+        thread::sleep(Duration::from_millis(100));
     };
+    if !matches!(exit, Exit::Status(_)) {
+        #[cfg(unix)]
+        let _ = process::Command::new("kill").args(["-KILL", "--", &format!("-{}", child.id())]).status();
+        let _ = child.kill();
+        child.wait()?;
+    }
 
-    let mut entries = fs::read_dir(dir_path).map_err(Error::from)?.filter_map(|e| e.ok()).collect::<Vec<_>>();
-    entries.sort_by_key(|e| e.path());
-    for entry in entries {
-        match entry.path().extension().map(|ext| { ext.to_str() }).flatten() {
-            Some(extension) if extension == runtime.payload_extension() => {
-                let bench_path = entry.path().to_owned();
-                let bench_name = bench_path.file_prefix().unwrap().to_owned();
-
-                let mut laze_args = Vec::from_iter(
-                    [
-                        "build",
-                        "-s", runtime.to_laze_module(),
-                        "-s", benchmark.to_laze_module(),
-                        "-b", &board,
-                    ]
-                );
-                if monitor_heap {
-                    laze_args.extend([
-                        "-s", "dynamic-memory-measure"
-                    ]);
-                }
+    let _ = stdout_thread.join();
+    stdout.extend(received.try_iter().flatten());
+    Ok((exit, stdout, stderr_thread.join().unwrap_or_default()))
+}
 
-                laze_args.push("run");
-                // FIXME: do better to know that this is indeed an esp32
-                if !board.contains("esp") {
-                    laze_args.extend([
-                        "--",
-                        "--log-format", "{s}",
-                        "--target-output-file", output_file.to_str().unwrap()
-                    ]);
-                }
+/// Removes the escape sequences used by the serial monitor to color the logs
+fn strip_ansi(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Control Sequence Introducer, ended by a character in `@..=~`
+            if chars.next() == Some('[') {
+                chars.by_ref().find(|c| ('@'..='~').contains(c));
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Extracts the `<name>, ...` row logged by the firmware from a line of the serial monitor, where it is preceded by
+/// the log level and maybe a timestamp
+fn serial_result_row(line: &str, name: &str) -> Option<String> {
+    let line = strip_ansi(line);
+    let prefix = format!("{name}, ");
+    let (start, _) = line.match_indices(&prefix).find(|(start, _)| {
+        line[..*start].chars().next_back().is_none_or(|c| c.is_whitespace() || c == ']' || c == ':')
+    })?;
+    Some(line[start..].trim_end().to_owned())
+}
+
+/// The error logged by the firmware as `<row_name>, error, <stage>, <detail>` in place of a result, if any
+fn firmware_error(logged: &str, row_name: &str) -> Option<String> {
+    let prefix = format!("{row_name}, error, ");
+    let error = logged.lines().find_map(|line| line.trim().strip_prefix(prefix.as_str()))?;
+    Some(match error.split_once(", ") {
+        Some((stage, detail)) => format!("{stage}: {detail}"),
+        None => error.to_owned(),
+    })
+}
 
+/// What happened to one payload, or to a whole combination when it was skipped
+#[derive(Debug)]
+struct Report {
+    board: String,
+    runtime: Runtime,
+    benchmark: Benchmark,
+    bench: Option<String>,
+    outcome: Outcome,
+}
+
+/// Runs every combination of boards, runtimes and benchmarks and remembers how each went
+struct Sweep {
+    results_dir: PathBuf,
+    probe: Option<String>,
+    monitor_heap: bool,
+    raw_samples: bool,
+    cycle_count: bool,
+    metering: bool,
+    timeout: Duration,
+    retries: u32,
+    failure_log: PathBuf,
+    /// Script the commands are written to with `--dry-run`
+    dry_run: Option<fs::File>,
+    build_only: bool,
+    wasmtime_version: String,
+    environment: Environment,
+    /// Last header written to each result file, it is written again whenever something changes
+    headers: HashMap<PathBuf, Vec<String>>,
+    /// Engine configuration hashes of the wasmtime payloads, per Pulley target and profile
+    config_hashes: HashMap<(&'static str, &'static str), String>,
+    /// Linear memory checksums logged by each runtime, per board and Embench benchmark
+    checksums: BTreeMap<(String, String), Vec<(Runtime, String)>>,
+    reports: Vec<Report>,
+}
+
+impl Sweep {
+    fn report(&mut self, board: &Board, runtime: Runtime, benchmark: Benchmark, bench: Option<&str>, outcome: Outcome) {
+        self.reports.push(Report {
+            board: board.name.clone(),
+            runtime,
+            benchmark,
+            bench: bench.map(str::to_owned),
+            outcome,
+        });
+    }
 
-                match probe.as_str() {
-                    s if s == String::default() => {}
-                    _ => laze_args.extend(["--probe", &probe]),
+    fn run(&mut self, board: &Board, runtime: Runtime, benchmark: Benchmark) -> Result<(), Error> {
+        let counts_cycles =
+            !self.cycle_count || benchmark != Benchmark::Embench1 || matches!(board.arch, Arch::ThumbV7 | Arch::ThumbV8);
+        let meters = !self.metering || (benchmark == Benchmark::Embench1 && runtime.can_meter());
+        // Asynchronous wasmtime calls run on a stack of their own, which it can't switch to on Xtensa
+        let switches_stacks = !matches!(benchmark, Benchmark::Preemption | Benchmark::AsyncHost)
+            || runtime.wasmtime_profile().is_none()
+            || !matches!(board.arch, Arch::Xtensa);
+        // Wasmtime is sliced with fuel
+        let slices = benchmark != Benchmark::Preemption || runtime.can_meter();
+        // The host functions of wasm-interpreter can't reach the memory of the instance calling them
+        let links_wasi = benchmark != Benchmark::Wasi || runtime != Runtime::WasmInterpreter;
+        let (Some(suite), Some(_), true, true, true, true, true, true) = (
+            benchmark.to_dirname(),
+            runtime.to_laze_module(),
+            board.supports(runtime),
+            counts_cycles,
+            meters,
+            switches_stacks,
+            slices,
+            links_wasi,
+        ) else {
+            let reason = if runtime.to_laze_module().is_none() {
+                "runtime isn't supported yet"
+            } else if benchmark.to_dirname().is_none() {
+                "benchmark suite isn't supported yet"
+            } else if !counts_cycles {
+                "cycles are only counted on Cortex-M boards"
+            } else if !meters && benchmark != Benchmark::Embench1 {
+                "only Embench is metered"
+            } else if !meters {
+                "only the wasmtime-fuel payloads can be metered"
+            } else if !switches_stacks {
+                "wasmtime can't switch stacks on Xtensa"
+            } else if !slices {
+                "only the wasmtime-fuel payloads can be sliced"
+            } else if !links_wasi {
+                "wasm-interpreter's host functions can't access the caller's memory"
+            } else {
+                "runtime isn't supported by the board"
+            };
+            self.report(board, runtime, benchmark, None, Outcome::Skipped(reason.to_owned()));
+            return Ok(());
+        };
+        let suite_dir = PathBuf::from("benchmarks").join(suite);
+        let dir_path = runtime.payload_dir(&suite_dir, &self.wasmtime_version, board.arch);
+
+        let payloads = match self.payloads(&dir_path, runtime, board.arch) {
+            Ok(payloads) => payloads,
+            Err(error) => {
+                let reason = error.to_string();
+                eprintln!("{:?}", miette::Report::new(error));
+                self.report(board, runtime, benchmark, None, Outcome::Skipped(reason));
+                return Ok(());
+            }
+        };
+
+        let header = self.environment.header(board, runtime, &suite_dir, &dir_path)?;
+        let output_file =
+            results_file(&self.results_dir, board, runtime, benchmark, self.monitor_heap, self.metering);
+        for bench_path in payloads {
+            let bench_name = bench_path.file_prefix().unwrap().to_str().unwrap().to_owned();
+            let outcome = self.run_payload(board, runtime, benchmark, &bench_path, &bench_name, &output_file, &header)?;
+            self.report(board, runtime, benchmark, Some(&bench_name), outcome);
+        }
+        Ok(())
+    }
+
+    /// Payloads of `dir` for `runtime`, checked before flashing anything
+    fn payloads(&mut self, dir: &Path, runtime: Runtime, arch: Arch) -> Result<Vec<PathBuf>, Error> {
+        if let Some(profile) = runtime.wasmtime_profile() {
+            let key = (arch.pulley_target(), profile);
+            let config_hash = match self.config_hashes.get(&key) {
+                Some(config_hash) => config_hash.clone(),
+                None => {
+                    let config_hash = firmware_config_hash(&self.wasmtime_version, arch, profile)?;
+                    self.config_hashes.insert(key, config_hash.clone());
+                    config_hash
                 }
-                match runtime {
-                    Runtime::Wamr | Runtime::WamrAOT | Runtime::WamrFast => {
-                        let cflag = match arch {
-                            Arch::ThumbV7 | Arch::ThumbV8 => {
-                                "TARGET_CFLAGS=--specs=nosys.specs "
-                            },
-                            _ => {
-                                ""
-                            }
-                        };
-                        println!(
-                            "{}={:?} {}=../{:?} {}={} {}={} {}laze {}",
-                            "BENCHMARK", bench_name,
-                            "BENCHMARK_PATH", bench_path,
-                            "WAMR_BUILD_PLATFORM", "ariel-os",
-                            "WAMR_BUILD_TARGET", arch.to_wamr_build_target(),
-                            cflag,
-                            laze_args.join(" ")
-                        );
-                    }
-                    _ => {
-                        println!("BENCHMARK={:?} BENCHMARK_PATH=../{:?} laze {}", bench_name, bench_path, laze_args.join(" "));
+            };
+            check_payload_manifest(dir, runtime, arch, &self.wasmtime_version, &config_hash)?;
+        }
+
+        let mut payloads = fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(runtime.payload_extension()))
+            .collect::<Vec<_>>();
+        payloads.sort();
+
+        if runtime.wasmtime_profile().is_some() {
+            for payload in &payloads {
+                check_cwasm(payload, runtime, arch, &self.wasmtime_version)?;
+            }
+        }
+        Ok(payloads)
+    }
+
+    /// The command building, flashing and running a payload, whose logs probe-rs writes to `target_output`
+    fn invocation(
+        &self,
+        board: &Board,
+        runtime: Runtime,
+        benchmark: Benchmark,
+        bench_path: &Path,
+        bench_name: &str,
+        target_output: &Path,
+    ) -> Invocation {
+        let mut args = vec![
+            "build".to_owned(),
+            "-s".to_owned(), runtime.to_laze_module().unwrap().to_owned(),
+            "-s".to_owned(), benchmark.to_laze_module().unwrap().to_owned(),
+            "-b".to_owned(), board.name.clone(),
+        ];
+        if self.monitor_heap {
+            args.extend(["-s".to_owned(), "dynamic-memory-measure".to_owned()]);
+        }
+        // Only the Embench harness times separate runs, CoreMark reports a single score
+        let samples_module = if self.cycle_count {
+            Some("cycle-count")
+        } else {
+            self.raw_samples.then_some("raw-samples")
+        };
+        if let (Benchmark::Embench1, Some(module)) = (benchmark, samples_module) {
+            args.extend(["-s".to_owned(), module.to_owned()]);
+        }
+        if self.metering {
+            args.extend(["-s".to_owned(), "metering".to_owned()]);
+        }
+        if let Some(heap_size) = board.heap_size {
+            args.extend(["-D".to_owned(), format!("heapsize_required={heap_size}")]);
+        }
+
+        if !self.build_only {
+            args.push("run".to_owned());
+            if board.log_transport == LogTransport::Rtt {
+                args.extend([
+                    "--".to_owned(),
+                    "--log-format".to_owned(), "{s}".to_owned(),
+                    "--target-output-file".to_owned(), target_output.to_str().unwrap().to_owned(),
+                ]);
+            }
+            if let Some(probe) = &self.probe {
+                args.extend(["--probe".to_owned(), probe.clone()]);
+            }
+        }
+
+        let mut envs = vec![
+            ("BENCHMARK", bench_name.to_owned()),
+            ("BENCHMARK_PATH", format!("../{}", bench_path.to_str().unwrap())),
+        ];
+        if let (Runtime::WasefireInterpreter, Some(budget)) = (runtime, board.wasefire_memory_budget) {
+            envs.push(("WASEFIRE_MEMORY_BUDGET", budget.to_string()));
+        }
+        if let Runtime::Wamr | Runtime::WamrAOT | Runtime::WamrFast = runtime {
+            envs.extend([
+                ("WAMR_BUILD_PLATFORM", "ariel-os".to_owned()),
+                ("WAMR_BUILD_TARGET", board.wamr_build_target.clone()),
+            ]);
+            // Counts the instructions down to a limit, the closest WAMR has to fuel
+            if self.metering {
+                envs.push(("WAMR_BUILD_INSTRUCTION_METERING", "1".to_owned()));
+            }
+            if let Some(cflags) = &board.target_cflags {
+                envs.push(("TARGET_CFLAGS", cflags.clone()));
+            }
+            if let Some(cc) = &board.target_cc {
+                envs.push(("TARGET_CC", cc.clone()));
+            }
+        }
+        Invocation { envs, args }
+    }
+
+    fn run_payload(
+        &mut self,
+        board: &Board,
+        runtime: Runtime,
+        benchmark: Benchmark,
+        bench_path: &Path,
+        bench_name: &str,
+        output_file: &Path,
+        header: &[String],
+    ) -> Result<Outcome, Error> {
+        // The firmware output is captured separately before being added to the results. Without this script to
+        // post-process them, the logs of a dry run go straight to the results
+        let capture = match self.dry_run {
+            Some(_) => std::path::absolute(output_file)?,
+            None => env::temp_dir().join("ariel-wasm-benchmark-output.txt"),
+        };
+        let invocation = self.invocation(board, runtime, benchmark, bench_path, bench_name, &capture);
+
+        if let Some(script) = &mut self.dry_run {
+            writeln!(script, "{}", invocation.command_line())?;
+            return Ok(Outcome::Planned);
+        }
+
+        let command_line = invocation.command_line();
+        println!("{command_line}");
+        let mut header = header.to_vec();
+        header.push(format!("# laze modules, {}", invocation.modules().join(" ")));
+
+        // CoreMark results of every runtime share a file, and are logged under the suite's name
+        let tag = (benchmark == Benchmark::CoreMark && !self.monitor_heap).then(|| value_name(&runtime));
+        let row_name = if tag.is_some() { "coremark" } else { bench_name };
+
+        // A broken build doesn't get any better by trying again
+        let attempts = if self.build_only { 1 } else { self.retries + 1 };
+        let mut outcome = Outcome::Success;
+        for attempt in 1..=attempts {
+            if let Err(error) = fs::remove_file(&capture) && error.kind() != io::ErrorKind::NotFound {
+                return Err(error.into());
+            }
+
+            let command = invocation.command();
+            // The serial monitor keeps running once the benchmark is over, it is stopped as soon as the result arrives
+            let mut serial_rows = Vec::new();
+            let (exit, stdout, stderr) = run_with_timeout(command, self.timeout, |line| {
+                if board.log_transport == LogTransport::Serial {
+                    if let Some(metadata) = serial_result_row(line, "# nominal_clock_hz") {
+                        serial_rows.push(metadata);
+                    } else if let Some(sample) = serial_result_row(line, "sample") {
+                        serial_rows.push(sample);
+                    } else if let Some(checksum) = serial_result_row(line, "checksum") {
+                        serial_rows.push(checksum);
+                    } else if let Some(fuel) = serial_result_row(line, "fuel") {
+                        serial_rows.push(fuel);
+                    } else if let Some(row) = serial_result_row(line, row_name) {
+                        serial_rows.push(row);
+                        return true;
                     }
                 }
+                false
+            })?;
 
-                let mut command = process::Command::new("laze");
-                command
-                    .env("BENCHMARK", bench_name.to_str().unwrap())
-                    .env("BENCHMARK_PATH", format!("../{}", bench_path.to_str().unwrap()));
-
-                let output = match runtime {
-                    Runtime::Wamr | Runtime::WamrAOT | Runtime::WamrFast => {
-                        command
-                            .env("WAMR_BUILD_PLATFORM", "ariel-os")
-                            .env("WAMR_BUILD_TARGET", arch.to_wamr_build_target());
-                        match arch {
-                            Arch::ThumbV7 | Arch::ThumbV8 => {
-                                command.env("TARGET_CFLAGS", "--specs=nosys.specs");
-                            }
-                            _ => { }
-                        }
-                        command
-                    },
-                    _ => {
-                        command
-                    }
+            let logged = match (exit, board.log_transport) {
+                (Exit::Status(status), _) if !status.success() => {
+                    println!("{}", String::from_utf8_lossy(&stderr));
+                    Err(Outcome::Failed(format!("laze {status}")))
                 }
-                    .args(&laze_args)
-                    .output()
-                    .map_err(Error::from)?;
+                (Exit::Timeout, _) => Err(Outcome::Timeout),
+                _ if self.build_only => return Ok(Outcome::Success),
+                (_, LogTransport::Serial) => Ok(serial_rows.join("\n")),
+                (_, LogTransport::Rtt) => match fs::read_to_string(&capture) {
+                    Ok(logged) => Ok(logged),
+                    Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+                    Err(error) => return Err(error.into()),
+                },
+            };
+            outcome = match logged {
+                Ok(logged) => match self.append_results(output_file, board, runtime, header.clone(), &logged, tag.as_deref())? {
+                    0 => Outcome::Failed("no result was logged".to_owned()),
+                    // The error row was written in place of the results, running again would only repeat it
+                    _ => return Ok(firmware_error(&logged, row_name).map_or(Outcome::Success, Outcome::Errored)),
+                },
+                Err(outcome) => outcome,
+            };
+
+            let summary = format!(
+                "{} {} {} {bench_name}, attempt {attempt}/{attempts}: {}",
+                board.name,
+                value_name(&runtime),
+                value_name(&benchmark),
+                match &outcome {
+                    Outcome::Failed(reason) => reason.clone(),
+                    _ => format!("timed out after {}s", self.timeout.as_secs()),
+                },
+            );
+            println!("{summary}, see {}", self.failure_log.display());
+            self.log_failure(&summary, &command_line, &stdout, &stderr)?;
+        }
+
+        if self.build_only {
+            return Ok(outcome);
+        }
+        // Leave an explicit gap in the results
+        let marker = format!("{row_name}, {}", outcome.marker().unwrap());
+        self.append_results(output_file, board, runtime, header, &marker, tag.as_deref())?;
+        Ok(outcome)
+    }
+
+    /// Appends the rows logged by the firmware to `output` and returns how many there were. The lines starting with
+    /// `#` are metadata, they are added to `header`, while raw samples and checksums go to their own files.
+    /// Rows are suffixed with `tag` when given, so that the runtimes sharing a file can be told apart
+    fn append_results(
+        &mut self,
+        output: &Path,
+        board: &Board,
+        runtime: Runtime,
+        mut header: Vec<String>,
+        logged: &str,
+        tag: Option<&str>,
+    ) -> Result<usize, Error> {
+        let lines = logged.lines().map(str::trim).filter(|line| !line.is_empty());
+        let (metadata, rows): (Vec<_>, Vec<_>) = lines.partition(|line| line.starts_with('#'));
+        let (samples, rows): (Vec<_>, Vec<_>) = rows.into_iter().partition(|line| line.starts_with("sample, "));
+        let (checksums, rows): (Vec<_>, Vec<_>) = rows.into_iter().partition(|line| line.starts_with("checksum, "));
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        header.extend(metadata.into_iter().map(str::to_owned));
 
-                let process::Output { status, stdout: _, stderr} = output;
+        if !samples.is_empty() {
+            self.write_rows(&samples_file(&self.results_dir, board, runtime), board, &header, &samples, None)?;
+        }
+        if !checksums.is_empty() {
+            self.write_rows(&checksums_file(&self.results_dir, board), board, &header, &checksums, Some(&value_name(&runtime)))?;
+        }
+        for checksum in checksums {
+            if let ["checksum", bench, hash] = checksum.split(", ").collect::<Vec<_>>()[..] {
+                let runtimes = self.checksums.entry((board.name.clone(), bench.to_owned())).or_default();
+                runtimes.push((runtime, hash.to_owned()));
+            }
+        }
+        self.write_rows(output, board, &header, &rows, tag)?;
+        Ok(rows.len())
+    }
+
+    /// Appends `rows` to `output`, after `header` unless the last rows written there had the same
+    fn write_rows(&mut self, output: &Path, board: &Board, header: &[String], rows: &[&str], tag: Option<&str>) -> Result<(), Error> {
+        if let Some(dir) = output.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let is_new = !output.exists();
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(output)?;
+        if is_new {
+            writeln!(file, "# {}", board.results_dir)?;
+        }
+        // Rows without any metadata from the firmware, such as failures, don't need the header to be repeated
+        if !self.headers.get(output).is_some_and(|last| last.starts_with(header)) {
+            for line in header {
+                writeln!(file, "{line}")?;
+            }
+            self.headers.insert(output.to_owned(), header.to_vec());
+        }
+        for row in rows {
+            match tag {
+                Some(tag) => writeln!(file, "{row}, {tag}")?,
+                None => writeln!(file, "{row}")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn log_failure(&self, summary: &str, command_line: &str, stdout: &[u8], stderr: &[u8]) -> Result<(), Error> {
+        if let Some(dir) = self.failure_log.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut log = fs::OpenOptions::new().create(true).append(true).open(&self.failure_log)?;
+        writeln!(log, "=== {summary}")?;
+        writeln!(log, "$ {command_line}")?;
+        writeln!(log, "--- stdout")?;
+        log.write_all(stdout)?;
+        writeln!(log, "--- stderr")?;
+        log.write_all(stderr)?;
+        writeln!(log)?;
+        Ok(())
+    }
+
+    fn print_summary(&self) {
+        let count = |f: fn(&Outcome) -> bool| self.reports.iter().filter(|report| f(&report.outcome)).count();
+        if self.dry_run.is_some() {
+            println!(
+                "\n{} commands written, {} skipped",
+                count(|outcome| matches!(outcome, Outcome::Planned)),
+                count(|outcome| matches!(outcome, Outcome::Skipped(_))),
+            );
+        } else {
+            println!(
+                "\n{} succeeded, {} failed, {} errored, {} timed out, {} skipped, {} with differing checksums",
+                count(|outcome| matches!(outcome, Outcome::Success)),
+                count(|outcome| matches!(outcome, Outcome::Failed(_))),
+                count(|outcome| matches!(outcome, Outcome::Errored(_))),
+                count(|outcome| matches!(outcome, Outcome::Timeout)),
+                count(|outcome| matches!(outcome, Outcome::Skipped(_))),
+                self.checksum_mismatches().len(),
+            );
+        }
+        for report in &self.reports {
+            let (status, reason) = match &report.outcome {
+                Outcome::Success | Outcome::Planned => continue,
+                Outcome::Failed(reason) => ("failed", reason.clone()),
+                Outcome::Errored(reason) => ("error", reason.clone()),
+                Outcome::Timeout => ("timeout", format!("no answer within {}s", self.timeout.as_secs())),
+                Outcome::Skipped(reason) => ("skipped", reason.clone()),
+            };
+            println!(
+                "  {status:<7} {} {} {}{}: {reason}",
+                report.board,
+                value_name(&report.runtime),
+                value_name(&report.benchmark),
+                report.bench.as_ref().map(|bench| format!(" {bench}")).unwrap_or_default(),
+            );
+        }
+        for mismatch in self.checksum_mismatches() {
+            println!("  {mismatch}");
+        }
+    }
 
-                if !status.success() {
-                    std::println!(
-                        "{}", String::from_utf8_lossy(&stderr)
-                    );
+    /// Describes every benchmark whose linear memory wasn't left in the same state by all the runtimes, which
+    /// points at a miscompilation or an interpreter bug rather than at a difference in speed
+    fn checksum_mismatches(&self) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        for ((board, bench), runtimes) in &self.checksums {
+            // Runtimes agreeing with each other are listed together, most of them first
+            let mut groups: Vec<(&str, Vec<String>)> = Vec::new();
+            for (runtime, hash) in runtimes {
+                match groups.iter_mut().find(|(group_hash, _)| group_hash == hash) {
+                    Some((_, names)) => names.push(value_name(runtime)),
+                    None => groups.push((hash, vec![value_name(runtime)])),
                 }
             }
-            _ => {
+            if groups.len() < 2 {
+                continue;
+            }
+            groups.sort_by_key(|(_, names)| std::cmp::Reverse(names.len()));
+            let groups = groups.iter().map(|(hash, names)| format!("{} {hash}", names.join(", "))).collect::<Vec<_>>();
+            mismatches.push(format!("{:<7} {board} {bench}: {}", "differs", groups.join("; ")));
+        }
+        mismatches
+    }
+}
+
+/// Starts the script written by `--dry-run`, which creates the results directories of `boards` first
+fn create_script(path: &Path, results_dir: &Path, boards: &[Board]) -> Result<fs::File, Error> {
+    let mut script = fs::File::create(path)?;
+    writeln!(script, "#!/bin/sh")?;
+    writeln!(script, "# Written by `scripts/run_benchmarks.rs --dry-run`, to be run from the root of the repository.")?;
+    writeln!(script, "# The RTT logs are appended to the results as is, so CoreMark rows miss the runtime name.")?;
+    writeln!(script, "# The espflash monitor has to be left once the result of a serial board is shown.")?;
+    writeln!(script, "set -e")?;
+    let mut dirs = boards.iter().map(|board| results_dir.join(&board.results_dir)).collect::<Vec<_>>();
+    dirs.dedup();
+    for dir in dirs {
+        writeln!(script, "mkdir -p {}", shell_quote(std::path::absolute(dir)?.to_str().unwrap()))?;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(script)
+}
+
+fn main() -> miette::Result<()> {
+    let args = Args::parse();
+    let benchmarks = select::<Benchmark>(&args.benchmark);
+    let runtimes = select::<Runtime>(&args.runtime);
+    let boards = BoardRegistry::read(&args.boards_file)?.select(&args.board, &args.boards_file)?;
+
+    let failure_log = args.failure_log.unwrap_or_else(|| args.results_dir.join("failures.log"));
+    let dry_run = args.dry_run.as_deref().map(|path| create_script(path, &args.results_dir, &boards)).transpose()?;
+    let mut sweep = Sweep {
+        results_dir: args.results_dir,
+        probe: args.probe,
+        monitor_heap: args.monitor,
+        raw_samples: args.raw_samples,
+        cycle_count: args.cycle_count,
+        metering: args.metering,
+        timeout: Duration::from_secs(args.timeout),
+        retries: args.retries,
+        failure_log,
+        dry_run,
+        build_only: args.build_only,
+        wasmtime_version: firmware_wasmtime_version()?,
+        environment: Environment::collect()?,
+        headers: HashMap::new(),
+        config_hashes: HashMap::new(),
+        checksums: BTreeMap::new(),
+        reports: Vec::new(),
+    };
+    for board in &boards {
+        for &runtime in &runtimes {
+            for &benchmark in &benchmarks {
+                sweep.run(board, runtime, benchmark)?;
             }
         }
     }
+    sweep.print_summary();
 
     Ok(())
-}
\ No newline at end of file
+}
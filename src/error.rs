@@ -0,0 +1,80 @@
+extern crate alloc;
+use alloc::{format, string::String};
+use core::fmt::Debug;
+
+/// Why a benchmark didn't produce its result. Logged as a result row by [`Error::log`] instead of panicking, so that
+/// the runner records it and moves on to the next benchmark
+#[derive(Debug)]
+pub enum Error {
+    /// The module, or the precompiled payload, was rejected
+    Parse(String),
+    /// Defining the host functions or resolving the imports of the module failed
+    Link(String),
+    /// Creating the instance failed, running out of memory or trapping in the start function
+    Instantiate(String),
+    /// The benchmark trapped, with the wasm frames when the runtime records them
+    Trap { code: String, backtrace: Option<String> },
+    /// A host function was called with something unexpected, or its results were refused
+    Host(String),
+    /// The benchmark ran to completion but its self-check failed
+    Check(String),
+    /// The benchmark needs something the runtime doesn't offer
+    Unsupported(String),
+}
+
+impl Error {
+    pub fn parse(error: impl Debug) -> Self {
+        Self::Parse(format!("{error:?}"))
+    }
+
+    pub fn link(error: impl Debug) -> Self {
+        Self::Link(format!("{error:?}"))
+    }
+
+    pub fn instantiate(error: impl Debug) -> Self {
+        Self::Instantiate(format!("{error:?}"))
+    }
+
+    pub fn trap(code: impl Debug) -> Self {
+        Self::Trap { code: format!("{code:?}"), backtrace: None }
+    }
+
+    pub fn host(error: impl Debug) -> Self {
+        Self::Host(format!("{error:?}"))
+    }
+
+    pub fn missing_export(name: &str) -> Self {
+        Self::Link(format!("no export named `{name}`"))
+    }
+
+    /// `__original_main` returns 0 when the benchmark verified its own results
+    pub fn check(result: impl Debug) -> Self {
+        Self::Check(format!("the benchmark returned {result:?}"))
+    }
+
+    /// Logs `<benchmark>, error, <stage>, <detail>` where the runner expects the result row. Messages are kept on a
+    /// single line, and only the last field may contain commas
+    pub fn log(&self) {
+        let (stage, detail) = match self {
+            Self::Parse(detail) => ("parse", detail.clone()),
+            Self::Link(detail) => ("link", detail.clone()),
+            Self::Instantiate(detail) => ("instantiate", detail.clone()),
+            Self::Trap { code, backtrace: None } => ("trap", code.clone()),
+            Self::Trap { code, backtrace: Some(backtrace) } => ("trap", format!("{code}, {backtrace}")),
+            Self::Host(detail) => ("host", detail.clone()),
+            Self::Check(detail) => ("check", detail.clone()),
+            Self::Unsupported(detail) => ("unsupported", detail.clone()),
+        };
+        let detail = detail.trim().replace('\n', " | ");
+        ariel_os::debug::log::info!("{}, error, {}, {}", row_name(), stage, detail.as_str());
+    }
+}
+
+/// Name of the row the runner waits for, see `main`
+fn row_name() -> &'static str {
+    if cfg!(all(feature = "coremark", not(feature = "monitor-heap"))) {
+        "coremark"
+    } else {
+        crate::benchmark_name!()
+    }
+}
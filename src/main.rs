@@ -3,8 +3,15 @@
 
 use ariel_os::{debug::{ExitCode, exit}, time};
 
+mod error;
 mod utils;
 
+#[cfg(feature = "wasi")]
+mod wasi;
+
+#[cfg(feature = "wasefire")]
+mod wasefire_memory;
+
 #[cfg(feature = "wasmi")]
 #[path = "wasmi.rs"]
 mod run_wasm;
@@ -31,30 +38,213 @@ use run_wasm::coremark::run_coremark as benchmark;
 #[cfg(feature = "embench-1")]
 use run_wasm::embench1::run_bench as run_embench1;
 
+#[cfg(feature = "host-calls")]
+use run_wasm::host_calls::run_bench as run_host_calls;
+
+#[cfg(feature = "linear-memory")]
+use run_wasm::linear_memory::run_bench as run_linear_memory;
+
+#[cfg(feature = "memory-growth")]
+use run_wasm::memory_growth::run_bench as run_memory_growth;
+
+#[cfg(feature = "preemption")]
+use run_wasm::preemption::run_bench as run_preemption;
+
+#[cfg(feature = "async-host")]
+use run_wasm::async_host::run_bench as run_async_host;
+
+#[cfg(feature = "multi-instance")]
+use run_wasm::multi_instance::run_bench as run_multi_instance;
+
+#[cfg(feature = "wasi")]
+use run_wasm::wasi::run_bench as run_wasi;
+
 #[ariel_os::task(autostart)]
 async fn main() {
+    // Recorded by the runner in the header of the results. It is the frequency the board is expected to run at, not
+    // one read back from the clock tree
+    #[cfg(any(context = "nrf52840", context = "rp2350", context = "esp32", context = "esp32c6"))]
+    ariel_os::debug::log::info!("# nominal_clock_hz, {}", NOMINAL_CLOCK_HZ);
+
+    // A benchmark that failed logs its error in place of the result, and the heap isn't measured
+    #[cfg(feature = "monitor-heap")]
+    #[allow(unused_mut)]
+    let mut failed = false;
+
     #[cfg(feature = "coremark")]
     {
         // Using coremark.minimal
         // https://github.com/wasm3/wasm-coremark/tree/main
         ariel_os::debug::log::debug!("Running CoreMark 1.0...");
-        let score = benchmark();
-        ariel_os::debug::log::info!("coremark, {:?}", score);
-        ariel_os::debug::log::debug!("Score: {:?}", score);
+        match benchmark() {
+            Ok(score) => {
+                ariel_os::debug::log::info!("coremark, {:?}", score);
+                ariel_os::debug::log::debug!("Score: {:?}", score);
+            }
+            Err(error) => {
+                error.log();
+                #[cfg(feature = "monitor-heap")]
+                {
+                    failed = true;
+                }
+            }
+        }
     }
 
     #[cfg(feature = "embench-1")]
     {
         ariel_os::debug::log::debug!("Running Embench 1.0 benchmark");
-        #[allow(unused_variables)]
-        let (score_mean, score_std, times_means, times_std) = run_embench1();
+        match run_embench1() {
+            #[allow(unused_variables)]
+            Ok((score_mean, score_std, times_means, times_std)) => {
+                #[cfg(not(feature = "monitor-heap"))]
+                ariel_os::debug::log::info!("{}, {}, {}, {}, {}", crate::benchmark_name!(), score_mean, score_std, times_means, times_std);
+            }
+            Err(error) => {
+                error.log();
+                #[cfg(feature = "monitor-heap")]
+                {
+                    failed = true;
+                }
+            }
+        }
+    }
 
-        #[cfg(not(feature = "monitor-heap"))]
-        ariel_os::debug::log::info!("{}, {}, {}, {}, {}", crate::benchmark_name!(), score_mean, score_std, times_means, times_std);
+    #[cfg(feature = "host-calls")]
+    {
+        ariel_os::debug::log::debug!("Running host call benchmark");
+        match run_host_calls() {
+            #[allow(unused_variables)]
+            Ok((calls_mean, calls_std)) => {
+                #[cfg(not(feature = "monitor-heap"))]
+                ariel_os::debug::log::info!("{}, {}, {}", crate::benchmark_name!(), calls_mean, calls_std);
+            }
+            Err(error) => {
+                error.log();
+                #[cfg(feature = "monitor-heap")]
+                {
+                    failed = true;
+                }
+            }
+        }
     }
 
-    #[cfg(feature = "monitor-heap")]
+    #[cfg(feature = "linear-memory")]
+    {
+        ariel_os::debug::log::debug!("Running linear memory benchmark");
+        match run_linear_memory() {
+            #[allow(unused_variables)]
+            Ok((iterations_mean, iterations_std)) => {
+                #[cfg(not(feature = "monitor-heap"))]
+                ariel_os::debug::log::info!("{}, {}, {}", crate::benchmark_name!(), iterations_mean, iterations_std);
+            }
+            Err(error) => {
+                error.log();
+                #[cfg(feature = "monitor-heap")]
+                {
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "memory-growth")]
+    {
+        ariel_os::debug::log::debug!("Running memory growth test");
+        match run_memory_growth() {
+            #[allow(unused_variables)]
+            Ok((pages_before, pages_after, heap_grown)) => {
+                #[cfg(not(feature = "monitor-heap"))]
+                ariel_os::debug::log::info!("{}, {}, {}, {}", crate::benchmark_name!(), pages_before, pages_after, heap_grown);
+            }
+            Err(error) => {
+                error.log();
+                #[cfg(feature = "monitor-heap")]
+                {
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "preemption")]
+    {
+        ariel_os::debug::log::debug!("Running preemption benchmark");
+        match run_preemption().await {
+            #[allow(unused_variables)]
+            Ok((run_ms, slowdown, slices, lateness_mean, lateness_max)) => {
+                #[cfg(not(feature = "monitor-heap"))]
+                ariel_os::debug::log::info!("{}, {}, {}, {}, {}, {}", crate::benchmark_name!(), run_ms, slowdown, slices, lateness_mean, lateness_max);
+            }
+            Err(error) => {
+                error.log();
+                #[cfg(feature = "monitor-heap")]
+                {
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "async-host")]
+    {
+        ariel_os::debug::log::debug!("Running async host function benchmark");
+        match run_async_host().await {
+            #[allow(unused_variables)]
+            Ok((overshoot, lateness_mean, lateness_max)) => {
+                #[cfg(not(feature = "monitor-heap"))]
+                ariel_os::debug::log::info!("{}, {}, {}, {}", crate::benchmark_name!(), overshoot, lateness_mean, lateness_max);
+            }
+            Err(error) => {
+                error.log();
+                #[cfg(feature = "monitor-heap")]
+                {
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "multi-instance")]
     {
+        ariel_os::debug::log::debug!("Running multi-instance benchmark");
+        match run_multi_instance() {
+            #[allow(unused_variables)]
+            Ok((instances, heap_base, heap_per_instance, iterations_per_second)) => {
+                #[cfg(not(feature = "monitor-heap"))]
+                ariel_os::debug::log::info!("{}, {}, {}, {}, {}", crate::benchmark_name!(), instances, heap_base, heap_per_instance, iterations_per_second);
+            }
+            Err(error) => {
+                error.log();
+                #[cfg(feature = "monitor-heap")]
+                {
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "wasi")]
+    {
+        ariel_os::debug::log::debug!("Running WASI command");
+        match run_wasi() {
+            #[allow(unused_variables)]
+            Ok((time_mean, time_max)) => {
+                #[cfg(not(feature = "monitor-heap"))]
+                ariel_os::debug::log::info!("{}, {}, {}", crate::benchmark_name!(), time_mean, time_max);
+            }
+            Err(error) => {
+                error.log();
+                #[cfg(feature = "monitor-heap")]
+                {
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "monitor-heap")]
+    if !failed {
         let max = critical_section::with(|cs| {
             instrumented_allocator::MAX.counters.borrow(cs)
                 .get().1
@@ -67,6 +257,14 @@ async fn main() {
 
 }
 
+/// Wakes up periodically while the preemption and async host benchmarks run, it only gets to run when the runtime
+/// yields
+#[cfg(any(feature = "preemption", feature = "async-host"))]
+#[ariel_os::task(autostart)]
+async fn scheduling_probe() {
+    utils::scheduling::probe().await;
+}
+
 #[cfg(feature = "embench-1")]
 static BENCH_SCORE: [(&str, u64);  19] = [
     ("aha-mont64", 4_004),
@@ -90,6 +288,20 @@ static BENCH_SCORE: [(&str, u64);  19] = [
     ("wikisort", 2_779),
 ];
 
+// Core clock the benchmarks are expected to run at with the default clock configuration of Ariel OS, a constant per
+// chip rather than a frequency read from the HAL
+#[cfg(context = "nrf52840")]
+const NOMINAL_CLOCK_HZ: u32 = 64_000_000;
+
+#[cfg(context = "rp2350")]
+const NOMINAL_CLOCK_HZ: u32 = 150_000_000;
+
+#[cfg(context = "esp32")]
+const NOMINAL_CLOCK_HZ: u32 = 240_000_000;
+
+#[cfg(context = "esp32c6")]
+const NOMINAL_CLOCK_HZ: u32 = 160_000_000;
+
 #[cfg(all(not(feature = "wasm-interpreter"), not(feature = "wasefire"),feature = "embench-1", not(feature = "monitor-heap")))]
 static BENCHMARK_LOOPS: usize = 100;
 
@@ -102,7 +314,67 @@ static BENCHMARK_LOOPS: usize = 5;
 #[cfg(all(feature = "monitor-heap", feature = "embench-1"))]
 static BENCHMARK_LOOPS: usize = 2;
 
-#[cfg(feature = "monitor-heap")]
+// Host calls made by every run of the host call benchmarks, and number of runs
+#[cfg(feature = "host-calls")]
+static HOST_CALLS: i32 = 10_000;
+
+#[cfg(feature = "host-calls")]
+static HOST_CALL_LOOPS: usize = 10;
+
+// Largest copy out of the linear memory asked by `memory-copy`
+#[cfg(feature = "host-calls")]
+const HOST_COPY_SIZE: usize = 256;
+
+// Iterations of the access pattern made by every run of the linear memory benchmarks, and number of runs
+#[cfg(feature = "linear-memory")]
+static MEMORY_ITERATIONS: i32 = 100;
+
+#[cfg(feature = "linear-memory")]
+static MEMORY_LOOPS: usize = 10;
+
+// Rounds of the busy loop made by every run of the preemption benchmark, and number of runs
+#[cfg(feature = "preemption")]
+static PREEMPTION_ROUNDS: i32 = 2_000;
+
+#[cfg(feature = "preemption")]
+static PREEMPTION_LOOPS: usize = 5;
+
+// Fuel a slice is given before the runtimes that count it yield, about eight rounds of the busy loop for wasmi
+#[cfg(feature = "preemption")]
+const SLICE_FUEL: u64 = 10_000;
+
+// Sleeps made by every run of the async host benchmark, how long each of them lasts, and number of runs
+#[cfg(feature = "async-host")]
+static SLEEPS: i32 = 50;
+
+#[cfg(feature = "async-host")]
+static SLEEP_MS: i32 = 2;
+
+#[cfg(feature = "async-host")]
+static SLEEP_LOOPS: usize = 5;
+
+// Copies of the module instantiated side by side, rounds of calls made to each of them in turn, and iterations of
+// every call. Three one-page memories already take most of the heap of the nRF52840
+#[cfg(feature = "multi-instance")]
+const INSTANCES: usize = 3;
+
+#[cfg(feature = "multi-instance")]
+static INSTANCE_ROUNDS: usize = 10;
+
+#[cfg(feature = "multi-instance")]
+static INSTANCE_ITERATIONS: i32 = 1_000;
+
+// Runs of the WASI commands, each one started in a new instance
+#[cfg(feature = "wasi")]
+static WASI_LOOPS: usize = 5;
+
+// Period of the task measuring the scheduling latency
+#[cfg(any(feature = "preemption", feature = "async-host"))]
+const PROBE_PERIOD_US: u64 = 1_000;
+
+// Also counts the heap allocated by `memory.grow` for the memory growth test, and by every instance for the
+// multi-instance benchmark
+#[cfg(any(feature = "monitor-heap", feature = "memory-growth", feature = "multi-instance"))]
 pub mod instrumented_allocator {
     use core::{alloc::GlobalAlloc, cell::Cell};
     use critical_section::Mutex;
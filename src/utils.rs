@@ -16,6 +16,344 @@ macro_rules! benchmark_file {
 #[cfg(all(feature = "wamr", feature = "embench-1"))]
 pub use alloc::SendCell;
 
+/// Geometric mean and geometric standard deviation of the Embench score and of the time taken by each run, in ms.
+#[cfg(feature = "embench-1")]
+pub fn embench_statistics(bench_name: &str, times_to_run: &[u64]) -> (f64, f64, f64, f64) {
+    use libm::{pow, exp, log, sqrt};
+    use ariel_os::debug::log::debug;
+    use crate::BENCH_SCORE;
+
+    #[cfg(feature = "raw-samples")]
+    log_samples(bench_name, times_to_run);
+
+    let runs = times_to_run.len() as f64;
+    let mut geo_mean = 1_f64;
+    let mut times_geo_mean = 1_f64;
+    let score_to_div = BENCH_SCORE.iter().find(|(b_name, _)| *b_name == bench_name).unwrap().1;
+
+    for dur in times_to_run.iter() {
+        let normalized_speed = score_to_div as f64 / *dur as f64;
+        geo_mean *= pow(normalized_speed as f64, 1_f64/runs);
+        times_geo_mean *= pow(*dur as f64, 1_f64/runs);
+    }
+
+    // sigma = exp( sqrt( 1/N sum( ln ( A_i / mean )^2 ) ) ) https://en.wikipedia.org/wiki/Geometric_standard_deviation
+    let mut times_geo_std = 0_f64;
+    let mut geo_std = 0_f64;
+    for dur in times_to_run.iter() {
+        let normalized_speed = score_to_div as f64 / *dur as f64;
+        let logged = log(normalized_speed / geo_mean);
+        geo_std += logged * logged;
+
+        let logged_times = log(*dur as f64 / times_geo_mean);
+        times_geo_std += logged_times * logged_times;
+    }
+    geo_std = exp(sqrt(1_f64 / runs * geo_std));
+    times_geo_std = exp(sqrt(1_f64 / runs * times_geo_std));
+
+    debug!("Benchmark results for {}:", bench_name);
+    debug!("(Geometric) Mean score: {}", geo_mean);
+    debug!("Geometric Standard Deviation Score: {}", geo_std);
+    debug!("Range: [{}, {}]", geo_mean / geo_std, geo_mean * geo_std);
+
+    debug!("Timing results:");
+    debug!("(Geometric) Mean time to completion: {}ms", times_geo_mean);
+    debug!("Geometric Standard Deviation Time: {}", times_geo_std);
+    debug!("Range(ms): [{}, {}]", times_geo_mean / times_geo_std, times_geo_mean * times_geo_std);
+
+    (geo_mean, geo_std, times_geo_mean, times_geo_std)
+}
+
+/// Geometric mean and geometric standard deviation of the operations per second of each run, timed in µs, for the
+/// microbenchmarks doing `per_run` operations every run.
+#[cfg(any(feature = "host-calls", feature = "linear-memory"))]
+pub fn rate_statistics(bench_name: &str, per_run: i32, times_to_run: &[u64]) -> (f64, f64) {
+    use libm::{exp, log, sqrt};
+    use ariel_os::debug::log::debug;
+
+    let runs = times_to_run.len() as f64;
+    let rates = times_to_run.iter().map(|&time| per_run as f64 * 1_000_000_f64 / time.max(1) as f64);
+
+    let geo_mean = exp(rates.clone().map(log).sum::<f64>() / runs);
+    let geo_std = exp(sqrt(rates.map(|rate| log(rate / geo_mean) * log(rate / geo_mean)).sum::<f64>() / runs));
+
+    debug!("Results for {}:", bench_name);
+    debug!("(Geometric) Mean operations per second: {}", geo_mean);
+    debug!("Geometric Standard Deviation: {}", geo_std);
+
+    (geo_mean, geo_std)
+}
+
+/// Mean time of a run to completion in ms, mean slowdown of the sliced runs, mean number of slices of a run, and
+/// mean and maximum lateness of the probe during the sliced runs in µs. Each run is
+/// `(to_completion_us, sliced_us, slices, lateness_mean_us, lateness_max_us)`
+#[cfg(feature = "preemption")]
+pub fn preemption_statistics(bench_name: &str, runs: &[(u64, u64, u32, f64, u32)]) -> (f64, f64, f64, f64, u32) {
+    use ariel_os::debug::log::debug;
+
+    let count = runs.len() as f64;
+    let to_completion = runs.iter().map(|run| run.0 as f64).sum::<f64>() / count;
+    let sliced = runs.iter().map(|run| run.1 as f64).sum::<f64>() / count;
+    let slices = runs.iter().map(|run| run.2 as f64).sum::<f64>() / count;
+    let lateness_mean = runs.iter().map(|run| run.3).sum::<f64>() / count;
+    let lateness_max = runs.iter().map(|run| run.4).max().unwrap_or(0);
+
+    debug!("Results for {}:", bench_name);
+    debug!("Mean time to completion: {}us, sliced: {}us in {} slices", to_completion, sliced, slices);
+    debug!("Lateness of the probe: {}us on average, {}us at most", lateness_mean, lateness_max);
+
+    (to_completion / 1000_f64, sliced / to_completion.max(1_f64), slices, lateness_mean, lateness_max)
+}
+
+/// Hands control back to the executor once, letting the other tasks that are ready run before the caller continues
+#[cfg(any(feature = "preemption", feature = "async-host"))]
+pub async fn yield_now() {
+    use core::task::Poll;
+
+    let mut yielded = false;
+    core::future::poll_fn(|context| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            context.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }).await
+}
+
+/// Mean time the host overslept by per sleep, and mean and maximum lateness of the probe meanwhile, in µs. Each run
+/// is `(run_us, lateness_mean_us, lateness_max_us)`
+#[cfg(feature = "async-host")]
+pub fn sleep_statistics(bench_name: &str, runs: &[(u64, f64, u32)]) -> (f64, f64, u32) {
+    use ariel_os::debug::log::debug;
+    use crate::{SLEEPS, SLEEP_MS};
+
+    let count = runs.len() as f64;
+    let slept = SLEEPS as f64 * SLEEP_MS as f64 * 1000_f64;
+    let overshoot = runs.iter().map(|run| (run.0 as f64 - slept) / SLEEPS as f64).sum::<f64>() / count;
+    let lateness_mean = runs.iter().map(|run| run.1).sum::<f64>() / count;
+    let lateness_max = runs.iter().map(|run| run.2).max().unwrap_or(0);
+
+    debug!("Results for {}:", bench_name);
+    debug!("Mean oversleep: {}us", overshoot);
+    debug!("Lateness of the probe: {}us on average, {}us at most", lateness_mean, lateness_max);
+
+    (overshoot, lateness_mean, lateness_max)
+}
+
+/// Ariel OS futures awaited by the host functions of the async host benchmark
+#[cfg(feature = "async-host")]
+pub mod host_futures {
+    use ariel_os::time::Timer;
+
+    /// `sleep_ms`, negative durations don't sleep
+    pub fn sleep(ms: i32) -> Timer {
+        Timer::after_millis(ms.max(0) as u64)
+    }
+
+    /// `sleep_ms` for the runtimes whose host functions can't be suspended, spinning until the deadline instead of
+    /// awaiting a timer. The other tasks don't run until it returns
+    #[cfg(any(feature = "wasmi", feature = "wasm-interpreter", feature = "wamr"))]
+    pub fn sleep_blocking(ms: i32) {
+        use ariel_os::time::{Duration, Instant};
+
+        let deadline = Instant::now() + Duration::from_millis(ms.max(0) as u64);
+        while Instant::now() < deadline {}
+    }
+}
+
+/// Lateness of a task woken up every [`PROBE_PERIOD_US`](crate::PROBE_PERIOD_US), which can only run when the
+/// benchmark yields
+#[cfg(any(feature = "preemption", feature = "async-host"))]
+pub mod scheduling {
+    use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    use ariel_os::time::{Duration, Instant, Timer};
+
+    use crate::PROBE_PERIOD_US;
+
+    static ACTIVE: AtomicBool = AtomicBool::new(false);
+    // Low bits of the time the measurement started at in µs, the probe isn't late for the time before
+    static STARTED: AtomicU32 = AtomicU32::new(0);
+    static TICKS: AtomicU32 = AtomicU32::new(0);
+    static LATENESS_SUM: AtomicU32 = AtomicU32::new(0);
+    static LATENESS_MAX: AtomicU32 = AtomicU32::new(0);
+
+    pub fn start() {
+        TICKS.store(0, Ordering::Relaxed);
+        LATENESS_SUM.store(0, Ordering::Relaxed);
+        LATENESS_MAX.store(0, Ordering::Relaxed);
+        STARTED.store(Instant::now().as_micros() as u32, Ordering::Relaxed);
+        ACTIVE.store(true, Ordering::Relaxed);
+    }
+
+    /// Mean and maximum lateness since [`start`], in µs. The probe is let run once more first, so that a benchmark
+    /// that kept it from running until the end is accounted for
+    pub async fn stop() -> (f64, u32) {
+        let ticks = TICKS.load(Ordering::Relaxed);
+        while TICKS.load(Ordering::Relaxed) == ticks {
+            super::yield_now().await;
+        }
+        ACTIVE.store(false, Ordering::Relaxed);
+        let ticks = TICKS.load(Ordering::Relaxed);
+        let mean = LATENESS_SUM.load(Ordering::Relaxed) as f64 / ticks as f64;
+        (mean, LATENESS_MAX.load(Ordering::Relaxed))
+    }
+
+    pub async fn probe() {
+        loop {
+            let deadline = Instant::now() + Duration::from_micros(PROBE_PERIOD_US);
+            Timer::at(deadline).await;
+            let now = Instant::now();
+            if ACTIVE.load(Ordering::Relaxed) {
+                let since_start = (now.as_micros() as u32).wrapping_sub(STARTED.load(Ordering::Relaxed));
+                let lateness = ((now - deadline).as_micros() as u32).min(since_start);
+                TICKS.fetch_add(1, Ordering::Relaxed);
+                LATENESS_SUM.fetch_add(lateness, Ordering::Relaxed);
+                LATENESS_MAX.fetch_max(lateness, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Instances run side by side, heap taken by the runtime and the module before the first instance, mean heap taken by
+/// an instance, and iterations per second of the round-robin calls to all of them, timed in µs
+#[cfg(feature = "multi-instance")]
+pub fn instance_statistics(bench_name: &str, heap_base: usize, heap_instances: usize, time: u64) -> (usize, usize, usize, f64) {
+    use ariel_os::debug::log::debug;
+    use crate::{INSTANCES, INSTANCE_ITERATIONS, INSTANCE_ROUNDS};
+
+    let heap_per_instance = heap_instances / INSTANCES;
+    let iterations = (INSTANCES * INSTANCE_ROUNDS) as f64 * INSTANCE_ITERATIONS as f64;
+    let iterations_per_second = iterations * 1_000_000_f64 / time.max(1) as f64;
+
+    debug!("Results for {}:", bench_name);
+    debug!("Heap: {} bytes before instantiating, {} bytes per instance", heap_base, heap_per_instance);
+    debug!("Iterations per second over {} instances: {}", INSTANCES, iterations_per_second);
+
+    (INSTANCES, heap_base, heap_per_instance, iterations_per_second)
+}
+
+/// Checks the totals returned by the last round of the multi-instance benchmark, the instance `i` adding `i + 1` at
+/// every iteration. Any state shared between the instances adds the ids of the others to its total
+#[cfg(feature = "multi-instance")]
+pub fn check_totals(totals: &[i32]) -> Result<(), crate::error::Error> {
+    use crate::{INSTANCE_ITERATIONS, INSTANCE_ROUNDS};
+
+    let isolated = totals.iter().zip(1..).all(|(&total, id)| total == id * INSTANCE_ITERATIONS * INSTANCE_ROUNDS as i32);
+    if isolated {
+        Ok(())
+    } else {
+        Err(crate::error::Error::check(totals))
+    }
+}
+
+/// Mean and maximum time a WASI command took to exit, in ms, each run being timed in µs
+#[cfg(feature = "wasi")]
+pub fn command_statistics(bench_name: &str, times_to_run: &[u64]) -> (f64, f64) {
+    use ariel_os::debug::log::debug;
+
+    let mean = times_to_run.iter().sum::<u64>() as f64 / times_to_run.len() as f64 / 1000_f64;
+    let max = times_to_run.iter().copied().max().unwrap_or(0) as f64 / 1000_f64;
+
+    debug!("Results for {}:", bench_name);
+    debug!("Mean time to exit: {}ms, at most {}ms", mean, max);
+
+    (mean, max)
+}
+
+/// Bytes currently allocated on the heap, as counted by the instrumented allocator
+#[cfg(any(feature = "memory-growth", feature = "multi-instance"))]
+pub fn heap_used() -> usize {
+    critical_section::with(|cs| crate::instrumented_allocator::MAX.counters.borrow(cs).get().0)
+}
+
+/// Logs a FNV-1a hash of the linear memory left by a run as `checksum, <benchmark>, <hash>`, for the runner to
+/// compare runtimes with. Trailing zeros are left out, as runtimes don't all allocate the same amount of memory.
+#[cfg(feature = "embench-1")]
+pub fn log_checksum(bench_name: &str, memory: &[u8]) {
+    let used = memory.iter().rposition(|&byte| byte != 0).map_or(0, |last| last + 1);
+    let hash = memory[..used].iter().fold(0x811c_9dc5_u32, |hash, &byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193));
+    ariel_os::debug::log::info!("checksum, {}, {:08x}", bench_name, hash);
+}
+
+/// Logs the fuel consumed by a run as `fuel, <benchmark>, <units>`, a proxy for the number of instructions it executed.
+/// Units differ between runtimes, a wasmi unit roughly being an instruction of its own bytecode.
+#[cfg(all(feature = "metering", feature = "embench-1"))]
+pub fn log_fuel(bench_name: &str, fuel: u64) {
+    ariel_os::debug::log::info!("fuel, {}, {}", bench_name, fuel);
+}
+
+/// Logs the time of every run, along with its cycle count with `cycle-count`, as
+/// `sample, <benchmark>, <run>, <ms>[, <cycles>]`
+#[cfg(all(feature = "raw-samples", feature = "embench-1"))]
+fn log_samples(bench_name: &str, times_to_run: &[u64]) {
+    use ariel_os::debug::log::info;
+
+    for (run, time) in times_to_run.iter().enumerate() {
+        #[cfg(feature = "cycle-count")]
+        info!("sample, {}, {}, {}, {}", bench_name, run, time, cycle_count::get(run).unwrap_or(0));
+
+        #[cfg(not(feature = "cycle-count"))]
+        info!("sample, {}, {}, {}", bench_name, run, time);
+    }
+}
+
+/// Cycles spent between `start_trigger` and `stop_trigger`, counted by the DWT unit of Cortex-M cores
+#[cfg(feature = "cycle-count")]
+pub mod cycle_count {
+    #[cfg(not(context = "cortex-m"))]
+    compile_error!("cycle-count relies on the DWT cycle counter, only found on Cortex-M cores");
+
+    use core::ptr;
+    use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+    const DEMCR: *mut u32 = 0xE000_EDFC as *mut u32;
+    const DWT_CTRL: *mut u32 = 0xE000_1000 as *mut u32;
+    const DWT_CYCCNT: *const u32 = 0xE000_1004 as *const u32;
+
+    // As many runs as the largest `BENCHMARK_LOOPS`
+    const MAX_RUNS: usize = 100;
+
+    static START: AtomicU32 = AtomicU32::new(0);
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    static CYCLES: [AtomicU32; MAX_RUNS] = [const { AtomicU32::new(0) }; MAX_RUNS];
+
+    pub fn start() {
+        // SAFETY: DEMCR and DWT_CTRL are memory-mapped registers present on every Cortex-M3 and above,
+        // setting TRCENA then CYCCNTENA only enables the cycle counter
+        unsafe {
+            ptr::write_volatile(DEMCR, ptr::read_volatile(DEMCR) | 1 << 24);
+            ptr::write_volatile(DWT_CTRL, ptr::read_volatile(DWT_CTRL) | 1);
+        }
+        START.store(now(), Ordering::Relaxed);
+    }
+
+    pub fn stop() {
+        // The counter is 32 bits wide, runs longer than 2^32 cycles are not told apart
+        let cycles = now().wrapping_sub(START.load(Ordering::Relaxed));
+        let run = RUNS.fetch_add(1, Ordering::Relaxed);
+        if let Some(slot) = CYCLES.get(run) {
+            slot.store(cycles, Ordering::Relaxed);
+        }
+    }
+
+    /// Cycle count of the `run`-th run, starting from 0
+    pub fn get(run: usize) -> Option<u32> {
+        if run < RUNS.load(Ordering::Relaxed) {
+            CYCLES.get(run).map(|cycles| cycles.load(Ordering::Relaxed))
+        } else {
+            None
+        }
+    }
+
+    fn now() -> u32 {
+        // SAFETY: DWT_CYCCNT is a memory-mapped register present on every Cortex-M3 and above
+        unsafe { ptr::read_volatile(DWT_CYCCNT) }
+    }
+}
+
 #[cfg(feature = "wamr")]
 mod alloc {
     // Allocator bindings required by WAMR
@@ -61,10 +399,11 @@ mod alloc {
         if let Ok(layout) = layout {
             let c_ptr = unsafe { alloc::alloc::alloc(layout) as *mut core::ffi::c_void };
             if c_ptr.is_null() {
+                // WAMR fails the module loading or instantiation that needed it, which is reported as an error
                 error!("[WAMR] not enough space left");
                 let total_in_wamr = C_ALLOCATIONS.borrow_mut().values().fold(0, |acc, lay| { acc + lay.size() });
                 error!("[WAMR] total of {} bytes in allocations", total_in_wamr);
-                panic!("[WAMR] Failed allocation")
+                return ptr::null_mut();
             }
             assert!(C_ALLOCATIONS.borrow_mut().insert(c_ptr, layout).is_none(), "[WAMR] Somehow already created this allocation...");
             return c_ptr;
@@ -90,7 +429,10 @@ mod alloc {
             alloc::alloc::realloc(addr as *mut u8, old_layout, size.try_into().unwrap()) as *mut core::ffi::c_void
         };
         if new_addr.is_null() {
-            panic!("[WAMR] failed realloc")
+            // The old allocation is left untouched
+            error!("[WAMR] not enough space left to reallocate");
+            C_ALLOCATIONS.borrow_mut().insert(addr, old_layout);
+            return ptr::null_mut();
         }
         C_ALLOCATIONS.borrow_mut().insert(new_addr, Layout::from_size_align(size as usize, old_layout.align()).unwrap());
         return new_addr
@@ -1,4 +1,4 @@
-use wamr_rust_sdk::{runtime::Runtime, module::Module, instance::Instance, function::Function};
+use wamr_rust_sdk::{runtime::Runtime, module::Module, instance::Instance, function::Function, value::WasmValue};
 
 extern crate alloc;
 
@@ -6,6 +6,8 @@ use core::ffi::c_void;
 
 use alloc::{vec, vec::Vec};
 
+use crate::error::Error;
+
 // Required to pull the tinyrlibc code that implements extern "C" functions needed by wamr
 extern crate tinyrlibc;
 
@@ -19,28 +21,28 @@ pub mod coremark {
         ariel_os::time::Instant::now().as_millis()
     }
 
-    pub fn run_coremark() -> f32 {
+    pub fn run_coremark() -> Result<f32, Error> {
         let wasm_bytes = Vec::from(include_bytes!(crate::benchmark_file!()));
 
         let runtime = Runtime::builder_with_module_name("env")
             .use_system_allocator()
             .run_as_interpreter()
             .register_host_function("clock_ms",  clock_ms as *mut c_void)
-            .build().unwrap();
+            .build().map_err(Error::link)?;
 
-        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").unwrap();
+        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").map_err(Error::parse)?;
 
         // 2KiB stack size
-        let instance = Instance::new(&runtime, &module, 1024 * 2).unwrap();
-
-        let function = Function::find_export_func(&instance, "run").unwrap();
+        let instance = Instance::new(&runtime, &module, 1024 * 2).map_err(Error::instantiate)?;
 
-        let res = function.call(&instance, &vec![])
-            .unwrap().into_iter().next().unwrap()
-            .into_f32()
-            .unwrap();
+        let function = Function::find_export_func(&instance, "run").map_err(Error::link)?;
 
-        return res;
+        // WAMR reports traps as an exception message, its call stack isn't dumped in this build
+        let results = function.call(&instance, &vec![]).map_err(Error::trap)?;
+        match results[..] {
+            [WasmValue::F32(score)] => Ok(score),
+            _ => Err(Error::check(&results)),
+        }
     }
 }
 
@@ -54,16 +56,15 @@ extern "C" fn ariel_time_get_boot_us() -> u64 {
 #[cfg(feature = "embench-1")]
 pub mod embench1 {
     use ariel_os::time::Instant;
-    use libm::{pow, exp, log, sqrt};
-    use ariel_os::debug::log::{debug, error};
+    use ariel_os::debug::log::debug;
 
     use super::*;
-    use crate::{BENCH_SCORE, BENCHMARK_LOOPS, benchmark_name, benchmark_file};
+    use crate::{BENCHMARK_LOOPS, benchmark_name, benchmark_file};
     use crate::utils::SendCell;
 
     static TIMINGS: SendCell<Vec<Instant>> = SendCell::new(Vec::new());
 
-    pub fn run_bench() -> (f64, f64, f64, f64) {
+    pub fn run_bench() -> Result<(f64, f64, f64, f64), Error> {
         let bench_name = benchmark_name!();
         let wasm_bytes = Vec::from(include_bytes!(benchmark_file!()));
 
@@ -73,76 +74,489 @@ pub mod embench1 {
             .register_host_function("initialise_board", initialise_board as *mut c_void)
             .register_host_function("start_trigger", start_trigger as *mut c_void)
             .register_host_function("stop_trigger", stop_trigger as *mut c_void)
-            .build().unwrap();
+            .build().map_err(Error::link)?;
 
-        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").unwrap();
+        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").map_err(Error::parse)?;
 
         // 4KiB stack size
-        let instance = Instance::new(&runtime, &module, 1024 * 4).unwrap();
+        let instance = Instance::new(&runtime, &module, 1024 * 4).map_err(Error::instantiate)?;
 
-        let function = Function::find_export_func(&instance, "__original_main").unwrap();
+        let function = Function::find_export_func(&instance, "__original_main").map_err(Error::link)?;
 
         debug!("Starting wasm app");
         for i in 1..=BENCHMARK_LOOPS {
             debug!("Run {}", i);
-            let correct = function.call(&instance, &vec![]).unwrap().into_iter().next().unwrap().into_i32();
-            match correct {
-                Ok(0) =>  { }
-                _ => {
-                    error!("Benchmarking went wrong from some reason, aborting");
-                    return (0_f64, 0_f64, 0_f64, 0_f64);
-                }
+            #[cfg(feature = "metering")]
+            reset_instruction_limit(&instance);
+            let results = function.call(&instance, &vec![]).map_err(Error::trap)?;
+            match results[..] {
+                // Every run starts from the same state, checking the first one is enough
+                [WasmValue::I32(0)] if i == 1 => crate::utils::log_checksum(bench_name, linear_memory(&instance)),
+                [WasmValue::I32(0)] =>  { }
+                _ => return Err(Error::check(&results)),
             }
         }
 
-        let mut geo_mean = 1_f64;
-        let mut times_geo_mean = 1_f64;
-        let score_to_div = BENCH_SCORE.iter().find(|(b_name, _)| *b_name == bench_name).unwrap().1;
-
         assert!(TIMINGS.borrow_mut().len() % 2 == 0);
 
         let durations = TIMINGS.borrow_mut().chunks(2).map(|instants| { (instants[1] - instants[0]).as_millis() }).collect::<Vec<_>>();
 
-        for dur in durations.iter() {
-            let normalized_speed = score_to_div as f64 / *dur as f64;
-            geo_mean *= pow(normalized_speed as f64, 1_f64/BENCHMARK_LOOPS as f64);
-            times_geo_mean *= pow(*dur as f64, 1_f64/BENCHMARK_LOOPS as f64);
-        }
-
-        // sigma = exp( sqrt( 1/N sum( ln ( A_i / mean )^2 ) ) ) https://en.wikipedia.org/wiki/Geometric_standard_deviation
-        let mut times_geo_std = 0_f64;
-        let mut geo_std = 0_f64;
-
-        for dur in durations.iter() {
-            let normalized_speed = score_to_div as f64 / *dur as f64;
-            let logged = log(normalized_speed / geo_mean);
-            geo_std += logged * logged;
+        Ok(crate::utils::embench_statistics(bench_name, &durations))
+    }
 
-            let logged_times = log(*dur as f64 / times_geo_mean);
-            times_geo_std += logged_times * logged_times;
+    /// Default memory of `instance`, with its current size
+    #[allow(unsafe_code)]
+    fn linear_memory(instance: &Instance) -> &[u8] {
+        use wamr_rust_sdk::sys::{
+            wasm_memory_get_base_address, wasm_memory_get_bytes_per_page, wasm_memory_get_cur_page_count,
+            wasm_runtime_get_default_memory,
+        };
+
+        unsafe {
+            let memory = wasm_runtime_get_default_memory(instance.get_inner_instance());
+            let size = wasm_memory_get_cur_page_count(memory) * wasm_memory_get_bytes_per_page(memory);
+            core::slice::from_raw_parts(wasm_memory_get_base_address(memory) as *const u8, size as usize)
         }
-        geo_std = exp(sqrt(1_f64 / BENCHMARK_LOOPS as f64 * geo_std));
-        times_geo_std = exp(sqrt(1_f64 / BENCHMARK_LOOPS as f64 * times_geo_std));
-
-        debug!("Benchmark results for {}:", bench_name);
-        debug!("(Geometric) Mean score: {}", geo_mean);
-        debug!("Geometric Standard Deviation Score: {}", geo_std);
-        debug!("Range: [{}, {}]", geo_mean / geo_std, geo_mean * geo_std);
-
-        debug!("Timing results:");
-        debug!("(Geometric) Mean time to completion: {}ms", times_geo_mean);
-        debug!("Geometric Standard Deviation Time: {}", times_geo_std);
-        debug!("Range(ms): [{}, {}]", times_geo_mean / times_geo_std, times_geo_mean * times_geo_std);
+    }
 
-        (geo_mean, geo_std, times_geo_mean, times_geo_std)
+    /// WAMR's instruction metering only counts down to a limit, set again before every run so that it never runs out.
+    /// The instructions executed can't be read back, only the slowdown is measured
+    #[cfg(feature = "metering")]
+    #[allow(unsafe_code)]
+    fn reset_instruction_limit(instance: &Instance) {
+        use wamr_rust_sdk::sys::{wasm_runtime_get_exec_env_singleton, wasm_runtime_set_instruction_count_limit};
+
+        // SAFETY: the functions of the instance are called in its singleton execution environment, created if needed
+        unsafe {
+            let exec_env = wasm_runtime_get_exec_env_singleton(instance.get_inner_instance());
+            wasm_runtime_set_instruction_count_limit(exec_env, i32::MAX);
+        }
     }
 
     extern "C" fn initialise_board() { }
 
     extern "C" fn start_trigger() {
         TIMINGS.borrow_mut().push(Instant::now());
+        #[cfg(feature = "cycle-count")]
+        crate::utils::cycle_count::start();
     }
     extern "C" fn stop_trigger() {
+        #[cfg(feature = "cycle-count")]
+        crate::utils::cycle_count::stop();
         TIMINGS.borrow_mut().push(Instant::now());
     }
 }
+
+#[cfg(feature = "host-calls")]
+pub mod host_calls {
+    use core::hint::black_box;
+    use core::ptr;
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+    use wamr_rust_sdk::sys::{
+        wasm_exec_env_t, wasm_runtime_addr_app_to_native, wasm_runtime_call_wasm, wasm_runtime_get_module_inst,
+        wasm_runtime_lookup_function, wasm_runtime_set_exception, wasm_runtime_validate_app_addr,
+    };
+
+    use super::*;
+    use crate::{HOST_CALLS, HOST_CALL_LOOPS, HOST_COPY_SIZE, benchmark_name, benchmark_file};
+
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm_bytes = Vec::from(include_bytes!(benchmark_file!()));
+
+        let runtime = Runtime::builder_with_module_name("env")
+            .use_system_allocator()
+            .run_as_interpreter()
+            .register_host_function("nop", nop as *mut c_void)
+            .register_host_function("take_i32", take_i32 as *mut c_void)
+            .register_host_function("take_i64", take_i64 as *mut c_void)
+            .register_host_function("take_f64", take_f64 as *mut c_void)
+            .register_host_function("copy", copy as *mut c_void)
+            .register_host_function("call_back", call_back as *mut c_void)
+            .build().map_err(Error::link)?;
+
+        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").map_err(Error::parse)?;
+
+        // 4KiB stack size
+        let instance = Instance::new(&runtime, &module, 1024 * 4).map_err(Error::instantiate)?;
+
+        let function = Function::find_export_func(&instance, "run").map_err(Error::link)?;
+
+        debug!("Starting wasm app");
+        let mut times_to_run = Vec::new();
+        for i in 1..=HOST_CALL_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            function.call(&instance, &vec![WasmValue::I32(HOST_CALLS)]).map_err(Error::trap)?;
+            times_to_run.push((Instant::now() - start).as_micros());
+        }
+        Ok(crate::utils::rate_statistics(bench_name, HOST_CALLS, &times_to_run))
+    }
+
+    // WAMR passes the execution environment first to every native function, before the wasm arguments
+
+    extern "C" fn nop(_: wasm_exec_env_t) { }
+
+    extern "C" fn take_i32(_: wasm_exec_env_t, value: i32) {
+        black_box(value);
+    }
+
+    extern "C" fn take_i64(_: wasm_exec_env_t, value: i64) {
+        black_box(value);
+    }
+
+    extern "C" fn take_f64(_: wasm_exec_env_t, value: f64) {
+        black_box(value);
+    }
+
+    #[allow(unsafe_code)]
+    extern "C" fn copy(exec_env: wasm_exec_env_t, ptr: u32, len: u32) {
+        // SAFETY: the range is checked against the linear memory of the calling instance before being read
+        unsafe {
+            let instance = wasm_runtime_get_module_inst(exec_env);
+            if len as usize > HOST_COPY_SIZE || !wasm_runtime_validate_app_addr(instance, ptr as _, len as _) {
+                // Turned into a trap once the function returns
+                wasm_runtime_set_exception(instance, c"copy out of bounds".as_ptr());
+                return;
+            }
+            let source = wasm_runtime_addr_app_to_native(instance, ptr as _) as *const u8;
+            let mut buffer = [0; HOST_COPY_SIZE];
+            ptr::copy_nonoverlapping(source, buffer.as_mut_ptr(), len as usize);
+            black_box(buffer);
+        }
+    }
+
+    #[allow(unsafe_code)]
+    extern "C" fn call_back(exec_env: wasm_exec_env_t) {
+        // SAFETY: `callback` is looked up in the calling instance, and called on its own execution environment
+        unsafe {
+            let instance = wasm_runtime_get_module_inst(exec_env);
+            let callback = wasm_runtime_lookup_function(instance, c"callback".as_ptr());
+            if callback.is_null() {
+                wasm_runtime_set_exception(instance, c"no callback to call".as_ptr());
+                return;
+            }
+            // A trap in the callback is left as the exception of the instance, which fails `run` in turn
+            wasm_runtime_call_wasm(exec_env, callback, 0, ptr::null_mut());
+        }
+    }
+}
+
+#[cfg(feature = "linear-memory")]
+pub mod linear_memory {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use super::*;
+    use crate::{MEMORY_ITERATIONS, MEMORY_LOOPS, benchmark_name, benchmark_file};
+
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm_bytes = Vec::from(include_bytes!(benchmark_file!()));
+
+        // The modules of the suite import nothing
+        let runtime = Runtime::builder()
+            .use_system_allocator()
+            .run_as_interpreter()
+            .build().map_err(Error::link)?;
+
+        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").map_err(Error::parse)?;
+
+        // 4KiB stack size
+        let instance = Instance::new(&runtime, &module, 1024 * 4).map_err(Error::instantiate)?;
+
+        let function = Function::find_export_func(&instance, "run").map_err(Error::link)?;
+
+        debug!("Starting wasm app");
+        let mut times_to_run = Vec::new();
+        for i in 1..=MEMORY_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            function.call(&instance, &vec![WasmValue::I32(MEMORY_ITERATIONS)]).map_err(Error::trap)?;
+            times_to_run.push((Instant::now() - start).as_micros());
+        }
+        Ok(crate::utils::rate_statistics(bench_name, MEMORY_ITERATIONS, &times_to_run))
+    }
+}
+
+#[cfg(feature = "memory-growth")]
+pub mod memory_growth {
+    use super::*;
+    use crate::{benchmark_file, utils::heap_used};
+
+    /// Pages of the memory before and after growing it by one, and the heap allocated by the growth
+    pub fn run_bench() -> Result<(i32, i32, usize), Error> {
+        let wasm_bytes = Vec::from(include_bytes!(benchmark_file!()));
+
+        let runtime = Runtime::builder()
+            .use_system_allocator()
+            .run_as_interpreter()
+            .build().map_err(Error::link)?;
+
+        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").map_err(Error::parse)?;
+
+        // 4KiB stack size
+        let instance = Instance::new(&runtime, &module, 1024 * 4).map_err(Error::instantiate)?;
+
+        let size = Function::find_export_func(&instance, "size").map_err(Error::link)?;
+        let grow = Function::find_export_func(&instance, "grow").map_err(Error::link)?;
+
+        let pages = |results: Vec<WasmValue>| match results[..] {
+            [WasmValue::I32(pages)] => Ok(pages),
+            _ => Err(Error::check(&results)),
+        };
+
+        let pages_before = pages(size.call(&instance, &vec![]).map_err(Error::trap)?)?;
+        let heap_before = heap_used();
+        grow.call(&instance, &vec![WasmValue::I32(1)]).map_err(Error::trap)?;
+        let heap_grown = heap_used().saturating_sub(heap_before);
+        let pages_after = pages(size.call(&instance, &vec![]).map_err(Error::trap)?)?;
+
+        Ok((pages_before, pages_after, heap_grown))
+    }
+}
+
+#[cfg(feature = "preemption")]
+pub mod preemption {
+    use super::*;
+
+    /// WAMR can terminate a call from another thread, but not suspend it and resume it later
+    pub async fn run_bench() -> Result<(f64, f64, f64, f64, u32), Error> {
+        Err(Error::Unsupported("WAMR runs a call to completion, it can't be suspended".into()))
+    }
+}
+
+#[cfg(feature = "async-host")]
+pub mod async_host {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+    use wamr_rust_sdk::sys::wasm_exec_env_t;
+
+    use super::*;
+    use crate::{SLEEPS, SLEEP_LOOPS, SLEEP_MS, benchmark_name, benchmark_file, utils::{host_futures, scheduling}};
+
+    /// Native functions can't be suspended, the sleep blocks the executor until it's over
+    pub async fn run_bench() -> Result<(f64, f64, u32), Error> {
+        let bench_name = benchmark_name!();
+        let wasm_bytes = Vec::from(include_bytes!(benchmark_file!()));
+
+        let runtime = Runtime::builder_with_module_name("env")
+            .use_system_allocator()
+            .run_as_interpreter()
+            .register_host_function("sleep_ms", sleep_ms as *mut c_void)
+            .build().map_err(Error::link)?;
+
+        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").map_err(Error::parse)?;
+
+        // 4KiB stack size
+        let instance = Instance::new(&runtime, &module, 1024 * 4).map_err(Error::instantiate)?;
+
+        let function = Function::find_export_func(&instance, "run").map_err(Error::link)?;
+
+        debug!("Starting wasm app");
+        let mut runs = Vec::new();
+        for i in 1..=SLEEP_LOOPS {
+            debug!("Run {}", i);
+            scheduling::start();
+            let start = Instant::now();
+            function.call(&instance, &vec![WasmValue::I32(SLEEPS), WasmValue::I32(SLEEP_MS)]).map_err(Error::trap)?;
+            let time = (Instant::now() - start).as_micros();
+            let (lateness_mean, lateness_max) = scheduling::stop().await;
+            runs.push((time, lateness_mean, lateness_max));
+        }
+        Ok(crate::utils::sleep_statistics(bench_name, &runs))
+    }
+
+    extern "C" fn sleep_ms(_: wasm_exec_env_t, ms: i32) {
+        host_futures::sleep_blocking(ms);
+    }
+}
+
+#[cfg(feature = "multi-instance")]
+pub mod multi_instance {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use super::*;
+    use crate::{INSTANCES, INSTANCE_ITERATIONS, INSTANCE_ROUNDS, benchmark_name, benchmark_file, utils::heap_used};
+
+    /// Every instance is made from the same loaded module, with its own linear memory and stack
+    pub fn run_bench() -> Result<(usize, usize, usize, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm_bytes = Vec::from(include_bytes!(benchmark_file!()));
+
+        let heap_start = heap_used();
+
+        // The modules of the suite import nothing
+        let runtime = Runtime::builder()
+            .use_system_allocator()
+            .run_as_interpreter()
+            .build().map_err(Error::link)?;
+
+        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").map_err(Error::parse)?;
+
+        let heap_base = heap_used().saturating_sub(heap_start);
+
+        // Dropped before the runtime, and allocated before measuring the heap of the instances
+        let mut instances = Vec::with_capacity(INSTANCES);
+        let mut functions = Vec::with_capacity(INSTANCES);
+        let heap_before = heap_used();
+
+        for _ in 0..INSTANCES {
+            // 4KiB stack size
+            instances.push(Instance::new(&runtime, &module, 1024 * 4).map_err(Error::instantiate)?);
+        }
+        for instance in instances.iter() {
+            functions.push(Function::find_export_func(instance, "run").map_err(Error::link)?);
+        }
+        let heap_instances = heap_used().saturating_sub(heap_before);
+
+        debug!("Starting wasm apps");
+        let mut totals = [0; INSTANCES];
+        let start = Instant::now();
+        for i in 1..=INSTANCE_ROUNDS {
+            debug!("Round {}", i);
+            for (index, (instance, function)) in instances.iter().zip(&functions).enumerate() {
+                let args = vec![WasmValue::I32(index as i32 + 1), WasmValue::I32(INSTANCE_ITERATIONS)];
+                let results = function.call(instance, &args).map_err(Error::trap)?;
+                totals[index] = match results[..] {
+                    [WasmValue::I32(total)] => total,
+                    _ => return Err(Error::check(&results)),
+                };
+            }
+        }
+        let time = (Instant::now() - start).as_micros();
+
+        crate::utils::check_totals(&totals)?;
+        Ok(crate::utils::instance_statistics(bench_name, heap_base, heap_instances, time))
+    }
+}
+
+#[cfg(feature = "wasi")]
+pub mod wasi {
+    use core::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+    use wamr_rust_sdk::sys::{
+        wasm_exec_env_t, wasm_memory_get_base_address, wasm_memory_get_bytes_per_page, wasm_memory_get_cur_page_count,
+        wasm_runtime_get_default_memory, wasm_runtime_get_module_inst, wasm_runtime_set_exception,
+    };
+
+    use super::*;
+    use crate::{WASI_LOOPS, benchmark_name, benchmark_file, wasi::{Exit, MODULE}};
+
+    // Status given to `proc_exit`, which stops the call with an exception
+    static EXITED: AtomicBool = AtomicBool::new(false);
+    static EXIT_STATUS: AtomicI32 = AtomicI32::new(0);
+
+    /// WAMR's own WASI isn't built, the subset is registered as native functions instead
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm_bytes = Vec::from(include_bytes!(benchmark_file!()));
+
+        let runtime = Runtime::builder_with_module_name(MODULE)
+            .use_system_allocator()
+            .run_as_interpreter()
+            .register_host_function("fd_write", fd_write as *mut c_void)
+            .register_host_function("clock_time_get", clock_time_get as *mut c_void)
+            .register_host_function("random_get", random_get as *mut c_void)
+            .register_host_function("args_get", args_get as *mut c_void)
+            .register_host_function("args_sizes_get", args_sizes_get as *mut c_void)
+            .register_host_function("environ_get", environ_get as *mut c_void)
+            .register_host_function("environ_sizes_get", environ_sizes_get as *mut c_void)
+            .register_host_function("fd_fdstat_get", fd_fdstat_get as *mut c_void)
+            .register_host_function("fd_close", fd_close as *mut c_void)
+            .register_host_function("fd_seek", fd_seek as *mut c_void)
+            .register_host_function("proc_exit", proc_exit as *mut c_void)
+            .build().map_err(Error::link)?;
+
+        let module = Module::from_vec(&runtime, wasm_bytes, "test-module").map_err(Error::parse)?;
+
+        debug!("Starting wasm app");
+        let mut times_to_run = Vec::new();
+        for i in 1..=WASI_LOOPS {
+            debug!("Run {}", i);
+            // A command is only started once, every run gets an instance of its own, with a 4KiB stack
+            let instance = Instance::new(&runtime, &module, 1024 * 4).map_err(Error::instantiate)?;
+            let function = Function::find_export_func(&instance, "_start").map_err(Error::link)?;
+            EXITED.store(false, Ordering::Relaxed);
+
+            let start = Instant::now();
+            let status = match function.call(&instance, &vec![]) {
+                Ok(_) => 0,
+                Err(_) if EXITED.load(Ordering::Relaxed) => EXIT_STATUS.load(Ordering::Relaxed),
+                Err(error) => return Err(Error::trap(error)),
+            };
+            times_to_run.push((Instant::now() - start).as_micros());
+
+            if status != 0 {
+                return Err(Error::check(Exit(status)));
+            }
+        }
+        Ok(crate::utils::command_statistics(bench_name, &times_to_run))
+    }
+
+    /// Default memory of the calling instance, with its current size
+    #[allow(unsafe_code)]
+    fn memory<'a>(exec_env: wasm_exec_env_t) -> &'a mut [u8] {
+        // SAFETY: the memory isn't resized or accessed by the instance until the native function returns
+        unsafe {
+            let memory = wasm_runtime_get_default_memory(wasm_runtime_get_module_inst(exec_env));
+            let size = wasm_memory_get_cur_page_count(memory) * wasm_memory_get_bytes_per_page(memory);
+            core::slice::from_raw_parts_mut(wasm_memory_get_base_address(memory) as *mut u8, size as usize)
+        }
+    }
+
+    extern "C" fn fd_write(exec_env: wasm_exec_env_t, fd: i32, iovs: i32, iovs_len: i32, nwritten: i32) -> i32 {
+        crate::wasi::fd_write(memory(exec_env), fd, iovs, iovs_len, nwritten)
+    }
+
+    extern "C" fn clock_time_get(exec_env: wasm_exec_env_t, id: i32, precision: i64, time: i32) -> i32 {
+        crate::wasi::clock_time_get(memory(exec_env), id, precision, time)
+    }
+
+    extern "C" fn random_get(exec_env: wasm_exec_env_t, buf: i32, len: i32) -> i32 {
+        crate::wasi::random_get(memory(exec_env), buf, len)
+    }
+
+    extern "C" fn args_get(exec_env: wasm_exec_env_t, argv: i32, argv_buf: i32) -> i32 {
+        crate::wasi::args_get(memory(exec_env), argv, argv_buf)
+    }
+
+    extern "C" fn args_sizes_get(exec_env: wasm_exec_env_t, argc: i32, argv_buf_size: i32) -> i32 {
+        crate::wasi::args_sizes_get(memory(exec_env), argc, argv_buf_size)
+    }
+
+    extern "C" fn environ_get(exec_env: wasm_exec_env_t, environ: i32, environ_buf: i32) -> i32 {
+        crate::wasi::environ_get(memory(exec_env), environ, environ_buf)
+    }
+
+    extern "C" fn environ_sizes_get(exec_env: wasm_exec_env_t, count: i32, buf_size: i32) -> i32 {
+        crate::wasi::environ_sizes_get(memory(exec_env), count, buf_size)
+    }
+
+    extern "C" fn fd_fdstat_get(exec_env: wasm_exec_env_t, fd: i32, stat: i32) -> i32 {
+        crate::wasi::fd_fdstat_get(memory(exec_env), fd, stat)
+    }
+
+    extern "C" fn fd_close(exec_env: wasm_exec_env_t, fd: i32) -> i32 {
+        crate::wasi::fd_close(memory(exec_env), fd)
+    }
+
+    extern "C" fn fd_seek(exec_env: wasm_exec_env_t, fd: i32, offset: i64, whence: i32, newoffset: i32) -> i32 {
+        crate::wasi::fd_seek(memory(exec_env), fd, offset, whence, newoffset)
+    }
+
+    #[allow(unsafe_code)]
+    extern "C" fn proc_exit(exec_env: wasm_exec_env_t, status: i32) {
+        EXIT_STATUS.store(status, Ordering::Relaxed);
+        EXITED.store(true, Ordering::Relaxed);
+        // SAFETY: the exception is set on the instance that called, which turns it into a trap once this returns
+        unsafe {
+            wasm_runtime_set_exception(wasm_runtime_get_module_inst(exec_env), c"proc_exit".as_ptr());
+        }
+    }
+}
@@ -1,12 +1,63 @@
+use core::ptr::NonNull;
+
+extern crate alloc;
+use alloc::alloc::{Layout, alloc_zeroed, dealloc};
+use alloc::format;
+
+use wasefire_interpreter::MEMORY_ALIGN;
+
+use crate::error::Error;
+
+/// The interpreter reports a trap without its cause, anything else is a misuse of the store such as a missing export
+fn call_error(error: wasefire_interpreter::Error) -> Error {
+    match error {
+        wasefire_interpreter::Error::Trap => Error::trap(error),
+        error => Error::link(error),
+    }
+}
+
+/// Linear memory lent to the interpreter, freed once the benchmark is done with it
+struct LinearMemory {
+    data: NonNull<u8>,
+    size: usize,
+    layout: Layout,
+}
+
+impl LinearMemory {
+    /// Memory sized by [`crate::wasefire_memory::memory_size`]
+    fn new(wasm: &[u8]) -> Result<Self, Error> {
+        let size = crate::wasefire_memory::memory_size(wasm).map_err(|error| Error::Instantiate(format!("{error}")))?;
+
+        // Allocating nothing is undefined, the interpreter is still only given `size` bytes
+        let layout = Layout::from_size_align(size.max(MEMORY_ALIGN), MEMORY_ALIGN).unwrap();
+        let data = NonNull::new(unsafe { alloc_zeroed(layout) })
+            .ok_or_else(|| Error::Instantiate(format!("no room for {size} bytes of linear memory")))?;
+        Ok(Self { data, size, layout })
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: `data` points to `size` zeroed bytes, borrowed for as long as `self` is
+        unsafe { core::slice::from_raw_parts_mut(self.data.as_ptr(), self.size) }
+    }
+}
+
+impl Drop for LinearMemory {
+    fn drop(&mut self) {
+        // SAFETY: `data` was allocated with `layout`, and the slices lent out don't outlive `self`
+        unsafe { dealloc(self.data.as_ptr(), self.layout) }
+    }
+}
+
 #[cfg(feature = "coremark")]
 pub mod coremark {
+    use super::*;
+
     extern crate alloc;
     use alloc::vec;
-    use alloc::alloc::{Layout, alloc_zeroed};
 
     use wasefire_interpreter::{FuncType, Module, RunResult, Store, Val, ValType, prepare};
 
-    pub fn run_coremark() -> f32 {
+    pub fn run_coremark() -> Result<f32, Error> {
         let wasm_bytes = include_bytes!(crate::benchmark_file!());
         let mut store = Store::default();
 
@@ -15,20 +66,16 @@ pub mod coremark {
             params: ().into(),
             results: ValType::I64.into()
         };
-        store.link_func_custom("env", "clock_ms", ftype).unwrap();
-
-        let wasm = prepare(wasm_bytes).unwrap();
-        let module = Module::new(&wasm).unwrap();
+        store.link_func_custom("env", "clock_ms", ftype).map_err(Error::link)?;
 
-        // Allocate Memory for the module, I don't know how much memory it needs so i'm going to give it 64KiB
-        // For some reason the memory needs to be 16-aligned so enforce this
-        let layout = Layout::from_size_align(64 * 1024, 16).unwrap();
-        let mut memory = unsafe { core::slice::from_raw_parts_mut(alloc_zeroed(layout), 64 * 1024) };
+        let mut memory = LinearMemory::new(wasm_bytes)?;
+        let wasm = prepare(wasm_bytes).map_err(Error::parse)?;
+        let module = Module::new(&wasm).map_err(Error::parse)?;
 
-        let inst = store.instantiate(module, &mut memory).unwrap();
+        let inst = store.instantiate(module, memory.as_mut_slice()).map_err(Error::instantiate)?;
 
         // Call the "run" function exported by the instance
-        let mut result = store.invoke(inst, "run", vec![]).unwrap();
+        let mut result = store.invoke(inst, "run", vec![]).map_err(call_error)?;
 
         // Process call from the module to the host until "run" terminates
         loop {
@@ -36,74 +83,78 @@ pub mod coremark {
                 // The function called into the host
                 RunResult::Host(call) => call,
                 RunResult::Done(results) => {
-                    assert_eq!(results.len(), 1);
-                    match results[0] {
-                        Val::F32(score) => return  f32::from_bits(score),
-                        _ => unreachable!()
+                    return match results[..] {
+                        [Val::F32(score)] => Ok(f32::from_bits(score)),
+                        _ => Err(Error::check(&results)),
                     }
                 }
             };
 
             // We only have a single linked function so the index should be 0.
-            assert_eq!(call.index(), 0);
-            result = call.resume(&[Val::I64(ariel_os::time::Instant::now().as_millis())]).unwrap();
+            if call.index() != 0 {
+                return Err(Error::Host(alloc::format!("unexpected call to host function {}", call.index())));
+            }
+            result = call.resume(&[Val::I64(ariel_os::time::Instant::now().as_millis())]).map_err(call_error)?;
         }
     }
 }
 
 #[cfg(feature = "embench-1")]
 pub mod embench1 {
+    use super::*;
+
     extern crate alloc;
     use alloc::{vec, vec::Vec};
-    use alloc::alloc::{Layout, alloc_zeroed};
 
     use wasefire_interpreter::{Module, RunResult, Store, Val, prepare};
 
     use ariel_os::time::Instant;
-    use libm::{pow, exp, log, sqrt};
-    use ariel_os::debug::log::{debug, error};
+    use ariel_os::debug::log::debug;
 
     const BENCHMARK_LOOPS: usize = 2;
-    use crate::{BENCH_SCORE, benchmark_name, benchmark_file};
+    use crate::{benchmark_name, benchmark_file};
 
-    pub fn run_bench() -> (f64, f64, f64, f64) {
+    pub fn run_bench() -> Result<(f64, f64, f64, f64), Error> {
         let bench_name = benchmark_name!();
         let wasm = include_bytes!(benchmark_file!());
 
-        let mut store = Store::default();
+        // The interpreter has neither fuel nor a count of the instructions it executed
+        if cfg!(feature = "metering") {
+            return Err(Error::Unsupported("wasefire has no fuel to meter".into()));
+        }
 
+        let mut store = Store::default();
 
-        store.link_func("env",  "initialise_board", 0, 0).unwrap();
-        store.link_func("env",  "start_trigger", 0, 0).unwrap();
-        store.link_func("env",  "stop_trigger", 0, 0).unwrap();
 
-        let wasm = prepare(wasm).unwrap();
-        let module = Module::new(&wasm).unwrap();
+        store.link_func("env",  "initialise_board", 0, 0).map_err(Error::link)?;
+        store.link_func("env",  "start_trigger", 0, 0).map_err(Error::link)?;
+        store.link_func("env",  "stop_trigger", 0, 0).map_err(Error::link)?;
 
-        // Allocate Memory for the module, I don't know how much memory it needs so i'm going to give it 64KiB
-        // For some reason the memory needs to be 16-aligned so enforce this
-        let layout = Layout::from_size_align(2 * 64 * 1024, 16).unwrap();
-        let mut memory = unsafe { core::slice::from_raw_parts_mut(alloc_zeroed(layout), 2 * 64 * 1024) };
+        let mut memory = LinearMemory::new(wasm)?;
+        let wasm = prepare(wasm).map_err(Error::parse)?;
+        let module = Module::new(&wasm).map_err(Error::parse)?;
 
-        let inst = store.instantiate(module, &mut memory).unwrap();
+        let inst = store.instantiate(module, memory.as_mut_slice()).map_err(Error::instantiate)?;
 
         let mut times_to_run = Vec::new();
         let mut start = Instant::now();
         let mut stop = Instant::now();
         for i in 1..=BENCHMARK_LOOPS {
             debug!("Run {}", i);
-            let mut result = store.invoke(inst, "__original_main", vec![]).unwrap();
+            let mut result = store.invoke(inst, "__original_main", vec![]).map_err(call_error)?;
             loop {
                 let call = match result {
                     RunResult::Done(correct) => {
                         match correct.first() {
                             Some(Val::I32(0)) => {
                                 times_to_run.push((stop - start).as_millis());
+                                // Every run starts from the same state, checking the first one is enough
+                                if i == 1 {
+                                    let memory = store.memory(inst).map_err(|_| Error::missing_export("memory"))?;
+                                    crate::utils::log_checksum(bench_name, memory);
+                                }
                             },
-                            _ => {
-                                error!("Benchmarking went wrong for some reason, aborting");
-                                return (0_f64, 0_f64, 0_f64, 0_f64);
-                            }
+                            _ => return Err(Error::check(&correct)),
                         }
                         break;
                     },
@@ -115,51 +166,474 @@ pub mod embench1 {
                     // start_trigger
                     1 => {
                         start = Instant::now();
+                        #[cfg(feature = "cycle-count")]
+                        crate::utils::cycle_count::start();
                     }
                     // stop_triger
                     2 => {
+                        #[cfg(feature = "cycle-count")]
+                        crate::utils::cycle_count::stop();
                         stop = Instant::now();
                     }
-                    _ => unreachable!(),
+                    index => return Err(Error::Host(alloc::format!("unexpected call to host function {index}"))),
                 }
-                result = call.resume(&[]).unwrap();
+                result = call.resume(&[]).map_err(call_error)?;
             }
         }
 
-        let mut geo_mean = 1_f64;
-        let mut times_geo_mean = 1_f64;
-        let score_to_div = BENCH_SCORE.iter().find(|(b_name, _)| *b_name == bench_name).unwrap().1;
+        Ok(crate::utils::embench_statistics(bench_name, &times_to_run))
+    }
+}
+#[cfg(feature = "host-calls")]
+pub mod host_calls {
+    use super::*;
 
-        for dur in times_to_run.iter() {
-            let normalized_speed = score_to_div as f64 / *dur as f64;
-            geo_mean *= pow(normalized_speed as f64, 1_f64/BENCHMARK_LOOPS as f64);
-            times_geo_mean *= pow(*dur as f64, 1_f64/BENCHMARK_LOOPS as f64);
+    extern crate alloc;
+    use alloc::{vec, vec::Vec};
+    use core::hint::black_box;
+
+    use wasefire_interpreter::{FuncType, Module, RunResult, Store, Val, ValType, prepare};
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use crate::{HOST_CALLS, HOST_CALL_LOOPS, HOST_COPY_SIZE, benchmark_name, benchmark_file};
+
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let mut store = Store::default();
+
+        // Host calls are identified by the order in which the functions are linked, which the store wants sorted
+        // by name
+        let take = |ty: ValType| FuncType { params: ty.into(), results: ().into() };
+        store.link_func("env", "call_back", 0, 0).map_err(Error::link)?;
+        store.link_func("env", "copy", 2, 0).map_err(Error::link)?;
+        store.link_func("env", "nop", 0, 0).map_err(Error::link)?;
+        store.link_func_custom("env", "take_f64", take(ValType::F64)).map_err(Error::link)?;
+        store.link_func("env", "take_i32", 1, 0).map_err(Error::link)?;
+        store.link_func_custom("env", "take_i64", take(ValType::I64)).map_err(Error::link)?;
+
+        let mut memory = LinearMemory::new(wasm)?;
+        let wasm = prepare(wasm).map_err(Error::parse)?;
+        let module = Module::new(&wasm).map_err(Error::parse)?;
+
+        let inst = store.instantiate(module, memory.as_mut_slice()).map_err(Error::instantiate)?;
+
+        debug!("Starting wasm app");
+        let mut times_to_run = Vec::new();
+        for i in 1..=HOST_CALL_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            let mut result = store.invoke(inst, "run", vec![Val::I32(HOST_CALLS as u32)]).map_err(call_error)?;
+            while let RunResult::Host(mut call) = result {
+                match call.index() {
+                    // A pending host call borrows the store, which can't be entered again until it's resumed
+                    0 => return Err(Error::Unsupported("calling into wasm from a host function".into())),
+                    1 => {
+                        let &[Val::I32(ptr), Val::I32(len)] = call.args() else {
+                            return Err(Error::Host(alloc::format!("copy called with {:?}", call.args())));
+                        };
+                        let (ptr, len) = (ptr as usize, len as usize);
+                        let mut buffer = [0; HOST_COPY_SIZE];
+                        let destination = buffer.get_mut(..len)
+                            .ok_or_else(|| Error::Host("copy larger than the host buffer".into()))?;
+                        let source = ptr.checked_add(len).and_then(|end| call.mem_mut().get(ptr..end))
+                            .ok_or_else(|| Error::Host("copy out of bounds".into()))?;
+                        destination.copy_from_slice(source);
+                        black_box(buffer);
+                    }
+                    2 => {}
+                    3..=5 => { black_box(call.args()); }
+                    index => return Err(Error::Host(alloc::format!("unexpected call to host function {index}"))),
+                }
+                result = call.resume(&[]).map_err(call_error)?;
+            }
+            times_to_run.push((Instant::now() - start).as_micros());
         }
+        Ok(crate::utils::rate_statistics(bench_name, HOST_CALLS, &times_to_run))
+    }
+}
 
-        // sigma = exp( sqrt( 1/N sum( ln ( A_i / mean )^2 ) ) ) https://en.wikipedia.org/wiki/Geometric_standard_deviation
-        let mut times_geo_std = 0_f64;
-        let mut geo_std = 0_f64;
-        for dur in times_to_run.iter() {
-            let normalized_speed = score_to_div as f64 / *dur as f64;
-            let logged = log(normalized_speed / geo_mean);
-            geo_std += logged * logged;
+#[cfg(feature = "linear-memory")]
+pub mod linear_memory {
+    use super::*;
 
-            let logged_times = log(*dur as f64 / times_geo_mean);
-            times_geo_std += logged_times * logged_times;
+    extern crate alloc;
+    use alloc::{vec, vec::Vec};
+
+    use wasefire_interpreter::{Module, RunResult, Store, Val, prepare};
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use crate::{MEMORY_ITERATIONS, MEMORY_LOOPS, benchmark_name, benchmark_file};
+
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        // The modules of the suite import nothing
+        let mut store = Store::default();
+
+        let mut memory = LinearMemory::new(wasm)?;
+        let wasm = prepare(wasm).map_err(Error::parse)?;
+        let module = Module::new(&wasm).map_err(Error::parse)?;
+
+        let inst = store.instantiate(module, memory.as_mut_slice()).map_err(Error::instantiate)?;
+
+        debug!("Starting wasm app");
+        let mut times_to_run = Vec::new();
+        for i in 1..=MEMORY_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            match store.invoke(inst, "run", vec![Val::I32(MEMORY_ITERATIONS as u32)]).map_err(call_error)? {
+                RunResult::Done(_) => {}
+                RunResult::Host(call) => {
+                    return Err(Error::Host(alloc::format!("unexpected call to host function {}", call.index())));
+                }
+            }
+            times_to_run.push((Instant::now() - start).as_micros());
         }
-        geo_std = exp(sqrt(1_f64 / BENCHMARK_LOOPS as f64 * geo_std));
-        times_geo_std = exp(sqrt(1_f64 / BENCHMARK_LOOPS as f64 * times_geo_std));
+        Ok(crate::utils::rate_statistics(bench_name, MEMORY_ITERATIONS, &times_to_run))
+    }
+}
+
+#[cfg(feature = "memory-growth")]
+pub mod memory_growth {
+    use super::*;
+
+    extern crate alloc;
+    use alloc::vec;
+
+    use wasefire_interpreter::{InstId, Module, RunResult, Store, Val, prepare};
+
+    use crate::{benchmark_file, utils::heap_used};
 
-        debug!("Benchmark results for {}:", bench_name);
-        debug!("(Geometric) Mean score: {}", geo_mean);
-        debug!("Geometric Standard Deviation Score: {}", geo_std);
-        debug!("Range: [{}, {}]", geo_mean / geo_std, geo_mean * geo_std);
+    /// Pages of the memory before and after growing it by one, and the heap allocated by the growth. The memory is
+    /// allocated up front for the declared maximum, growing it within that doesn't allocate anything
+    pub fn run_bench() -> Result<(i32, i32, usize), Error> {
+        let wasm = include_bytes!(benchmark_file!());
+
+        let mut store = Store::default();
+
+        let mut memory = LinearMemory::new(wasm)?;
+        let wasm = prepare(wasm).map_err(Error::parse)?;
+        let module = Module::new(&wasm).map_err(Error::parse)?;
 
-        debug!("Timing results:");
-        debug!("(Geometric) Mean time to completion: {}ms", times_geo_mean);
-        debug!("Geometric Standard Deviation Time: {}", times_geo_std);
-        debug!("Range(ms): [{}, {}]", times_geo_mean / times_geo_std, times_geo_mean * times_geo_std);
+        let inst = store.instantiate(module, memory.as_mut_slice()).map_err(Error::instantiate)?;
 
-        (geo_mean, geo_std, times_geo_mean, times_geo_std)
+        let pages_before = call(&mut store, inst, "size", vec![])?;
+        let heap_before = heap_used();
+        call(&mut store, inst, "grow", vec![Val::I32(1)])?;
+        let heap_grown = heap_used().saturating_sub(heap_before);
+        let pages_after = call(&mut store, inst, "size", vec![])?;
+
+        Ok((pages_before, pages_after, heap_grown))
     }
-}
\ No newline at end of file
+
+    /// Calls `name`, which imports nothing and returns a number of pages
+    fn call(store: &mut Store<'_>, inst: InstId, name: &str, args: alloc::vec::Vec<Val>) -> Result<i32, Error> {
+        match store.invoke(inst, name, args).map_err(call_error)? {
+            RunResult::Done(results) => match results[..] {
+                [Val::I32(pages)] => Ok(pages as i32),
+                _ => Err(Error::check(&results)),
+            },
+            RunResult::Host(call) => Err(Error::Host(alloc::format!("unexpected call to host function {}", call.index()))),
+        }
+    }
+}
+
+#[cfg(feature = "preemption")]
+pub mod preemption {
+    use super::*;
+
+    extern crate alloc;
+    use alloc::{vec, vec::Vec};
+
+    use wasefire_interpreter::{InstId, Module, RunResult, Store, Val, prepare};
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use crate::{PREEMPTION_LOOPS, PREEMPTION_ROUNDS, benchmark_name, benchmark_file, utils::scheduling};
+
+    pub async fn run_bench() -> Result<(f64, f64, f64, f64, u32), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let mut store = Store::default();
+
+        store.link_func("env", "checkpoint", 0, 0).map_err(Error::link)?;
+
+        let mut memory = LinearMemory::new(wasm)?;
+        let wasm = prepare(wasm).map_err(Error::parse)?;
+        let module = Module::new(&wasm).map_err(Error::parse)?;
+
+        let inst = store.instantiate(module, memory.as_mut_slice()).map_err(Error::instantiate)?;
+
+        debug!("Starting wasm app");
+        let mut runs = Vec::new();
+        for i in 1..=PREEMPTION_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            let (expected, _) = run(&mut store, inst, false).await?;
+            let to_completion = (Instant::now() - start).as_micros();
+
+            scheduling::start();
+            let start = Instant::now();
+            let (state, slices) = run(&mut store, inst, true).await?;
+            let sliced = (Instant::now() - start).as_micros();
+            let (lateness_mean, lateness_max) = scheduling::stop().await;
+
+            if state != expected {
+                return Err(Error::check(state));
+            }
+            runs.push((to_completion, sliced, slices, lateness_mean, lateness_max));
+        }
+        Ok(crate::utils::preemption_statistics(bench_name, &runs))
+    }
+
+    /// Runs the busy loop and returns its final state and the number of slices it ran in. The interpreter can only be
+    /// suspended by a host call, so a `sliced` run yields at every checkpoint of the loop
+    async fn run(store: &mut Store<'_>, inst: InstId, sliced: bool) -> Result<(i32, u32), Error> {
+        let mut slices = 1;
+        let mut result = store.invoke(inst, "run", vec![Val::I32(PREEMPTION_ROUNDS as u32)]).map_err(call_error)?;
+        loop {
+            match result {
+                RunResult::Done(results) => match results[..] {
+                    [Val::I32(state)] => return Ok((state as i32, slices)),
+                    _ => return Err(Error::check(&results)),
+                },
+                RunResult::Host(call) if call.index() == 0 => {
+                    if sliced {
+                        crate::utils::yield_now().await;
+                        slices += 1;
+                    }
+                    result = call.resume(&[]).map_err(call_error)?;
+                }
+                RunResult::Host(call) => {
+                    return Err(Error::Host(alloc::format!("unexpected call to host function {}", call.index())));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async-host")]
+pub mod async_host {
+    use super::*;
+
+    extern crate alloc;
+    use alloc::{vec, vec::Vec};
+
+    use wasefire_interpreter::{Module, RunResult, Store, Val, prepare};
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use crate::{SLEEPS, SLEEP_LOOPS, SLEEP_MS, benchmark_name, benchmark_file, utils::{host_futures, scheduling}};
+
+    /// Host calls suspend the interpreter, which is resumed once the sleep was awaited
+    pub async fn run_bench() -> Result<(f64, f64, u32), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let mut store = Store::default();
+
+        store.link_func("env", "sleep_ms", 1, 0).map_err(Error::link)?;
+
+        let mut memory = LinearMemory::new(wasm)?;
+        let wasm = prepare(wasm).map_err(Error::parse)?;
+        let module = Module::new(&wasm).map_err(Error::parse)?;
+
+        let inst = store.instantiate(module, memory.as_mut_slice()).map_err(Error::instantiate)?;
+
+        debug!("Starting wasm app");
+        let mut runs = Vec::new();
+        for i in 1..=SLEEP_LOOPS {
+            debug!("Run {}", i);
+            scheduling::start();
+            let start = Instant::now();
+            let args = vec![Val::I32(SLEEPS as u32), Val::I32(SLEEP_MS as u32)];
+            let mut result = store.invoke(inst, "run", args).map_err(call_error)?;
+            while let RunResult::Host(call) = result {
+                let (0, &[Val::I32(ms)]) = (call.index(), call.args()) else {
+                    return Err(Error::Host(alloc::format!("unexpected call to host function {} with {:?}", call.index(), call.args())));
+                };
+                host_futures::sleep(ms as i32).await;
+                result = call.resume(&[]).map_err(call_error)?;
+            }
+            let time = (Instant::now() - start).as_micros();
+            let (lateness_mean, lateness_max) = scheduling::stop().await;
+            runs.push((time, lateness_mean, lateness_max));
+        }
+        Ok(crate::utils::sleep_statistics(bench_name, &runs))
+    }
+}
+
+#[cfg(feature = "multi-instance")]
+pub mod multi_instance {
+    use super::*;
+
+    extern crate alloc;
+    use alloc::{vec, vec::Vec};
+
+    use wasefire_interpreter::{Module, RunResult, Store, Val, prepare};
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use crate::{INSTANCES, INSTANCE_ITERATIONS, INSTANCE_ROUNDS, benchmark_name, benchmark_file, utils::heap_used};
+
+    /// Every instance lives in the same store and shares the prepared module with the others, its linear memory
+    /// being allocated on its own
+    pub fn run_bench() -> Result<(usize, usize, usize, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        // Allocated up front to leave them out of the heap measurements
+        let mut memories = Vec::with_capacity(INSTANCES);
+        let mut insts = Vec::with_capacity(INSTANCES);
+        let heap_start = heap_used();
+
+        let prepared = prepare(wasm).map_err(Error::parse)?;
+        let module = Module::new(&prepared).map_err(Error::parse)?;
+
+        let mut store = Store::default();
+
+        let heap_base = heap_used().saturating_sub(heap_start);
+
+        for _ in 0..INSTANCES {
+            memories.push(LinearMemory::new(wasm)?);
+        }
+        for memory in memories.iter_mut() {
+            insts.push(store.instantiate(module.clone(), memory.as_mut_slice()).map_err(Error::instantiate)?);
+        }
+        let heap_instances = heap_used().saturating_sub(heap_start + heap_base);
+
+        debug!("Starting wasm apps");
+        let mut totals = [0; INSTANCES];
+        let start = Instant::now();
+        for i in 1..=INSTANCE_ROUNDS {
+            debug!("Round {}", i);
+            for (index, &inst) in insts.iter().enumerate() {
+                let args = vec![Val::I32(index as u32 + 1), Val::I32(INSTANCE_ITERATIONS as u32)];
+                totals[index] = match store.invoke(inst, "run", args).map_err(call_error)? {
+                    RunResult::Done(results) => match results[..] {
+                        [Val::I32(total)] => total as i32,
+                        _ => return Err(Error::check(&results)),
+                    },
+                    RunResult::Host(call) => {
+                        return Err(Error::Host(alloc::format!("unexpected call to host function {}", call.index())));
+                    }
+                };
+            }
+        }
+        let time = (Instant::now() - start).as_micros();
+
+        crate::utils::check_totals(&totals)?;
+        Ok(crate::utils::instance_statistics(bench_name, heap_base, heap_instances, time))
+    }
+}
+
+#[cfg(feature = "wasi")]
+pub mod wasi {
+    use super::*;
+
+    extern crate alloc;
+    use alloc::{vec, vec::Vec};
+
+    use wasefire_interpreter::{FuncType, Module, RunResult, Store, Val, ValType, prepare};
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use crate::{WASI_LOOPS, benchmark_name, benchmark_file, wasi::{Exit, MODULE}};
+
+    /// Linked in this order, which the store wants sorted by name, host calls are told apart by their index
+    const FUNCTIONS: [(&str, &[ValType], &[ValType]); 11] = [
+        ("args_get", &[ValType::I32; 2], &[ValType::I32]),
+        ("args_sizes_get", &[ValType::I32; 2], &[ValType::I32]),
+        ("clock_time_get", &[ValType::I32, ValType::I64, ValType::I32], &[ValType::I32]),
+        ("environ_get", &[ValType::I32; 2], &[ValType::I32]),
+        ("environ_sizes_get", &[ValType::I32; 2], &[ValType::I32]),
+        ("fd_close", &[ValType::I32], &[ValType::I32]),
+        ("fd_fdstat_get", &[ValType::I32; 2], &[ValType::I32]),
+        ("fd_seek", &[ValType::I32, ValType::I64, ValType::I32, ValType::I32], &[ValType::I32]),
+        ("fd_write", &[ValType::I32; 4], &[ValType::I32]),
+        ("proc_exit", &[ValType::I32], &[]),
+        ("random_get", &[ValType::I32; 2], &[ValType::I32]),
+    ];
+
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let prepared = prepare(wasm).map_err(Error::parse)?;
+        let module = Module::new(&prepared).map_err(Error::parse)?;
+
+        debug!("Starting wasm app");
+        let mut times_to_run = Vec::new();
+        for i in 1..=WASI_LOOPS {
+            debug!("Run {}", i);
+            // A command is only started once, every run gets an instance and a memory of its own
+            let mut memory = LinearMemory::new(wasm)?;
+            let mut store = Store::default();
+            for (name, params, results) in FUNCTIONS {
+                let ftype = FuncType { params: params.into(), results: results.into() };
+                store.link_func_custom(MODULE, name, ftype).map_err(Error::link)?;
+            }
+            let inst = store.instantiate(module.clone(), memory.as_mut_slice()).map_err(Error::instantiate)?;
+
+            let start = Instant::now();
+            let mut result = store.invoke(inst, "_start", vec![]).map_err(call_error)?;
+            let status = loop {
+                let mut call = match result {
+                    RunResult::Done(_) => break 0,
+                    RunResult::Host(call) => call,
+                };
+                let args = call.args().to_vec();
+                let errno = match (call.index(), &args[..]) {
+                    (0, &[Val::I32(argv), Val::I32(argv_buf)]) => {
+                        crate::wasi::args_get(call.mem_mut(), argv as i32, argv_buf as i32)
+                    }
+                    (1, &[Val::I32(argc), Val::I32(argv_buf_size)]) => {
+                        crate::wasi::args_sizes_get(call.mem_mut(), argc as i32, argv_buf_size as i32)
+                    }
+                    (2, &[Val::I32(id), Val::I64(precision), Val::I32(time)]) => {
+                        crate::wasi::clock_time_get(call.mem_mut(), id as i32, precision as i64, time as i32)
+                    }
+                    (3, &[Val::I32(environ), Val::I32(environ_buf)]) => {
+                        crate::wasi::environ_get(call.mem_mut(), environ as i32, environ_buf as i32)
+                    }
+                    (4, &[Val::I32(count), Val::I32(buf_size)]) => {
+                        crate::wasi::environ_sizes_get(call.mem_mut(), count as i32, buf_size as i32)
+                    }
+                    (5, &[Val::I32(fd)]) => crate::wasi::fd_close(call.mem_mut(), fd as i32),
+                    (6, &[Val::I32(fd), Val::I32(stat)]) => crate::wasi::fd_fdstat_get(call.mem_mut(), fd as i32, stat as i32),
+                    (7, &[Val::I32(fd), Val::I64(offset), Val::I32(whence), Val::I32(newoffset)]) => {
+                        crate::wasi::fd_seek(call.mem_mut(), fd as i32, offset as i64, whence as i32, newoffset as i32)
+                    }
+                    (8, &[Val::I32(fd), Val::I32(iovs), Val::I32(iovs_len), Val::I32(nwritten)]) => {
+                        crate::wasi::fd_write(call.mem_mut(), fd as i32, iovs as i32, iovs_len as i32, nwritten as i32)
+                    }
+                    // The call is never resumed
+                    (9, &[Val::I32(status)]) => break status as i32,
+                    (10, &[Val::I32(buf), Val::I32(len)]) => crate::wasi::random_get(call.mem_mut(), buf as i32, len as i32),
+                    (index, args) => {
+                        return Err(Error::Host(alloc::format!("unexpected call to host function {index} with {args:?}")));
+                    }
+                };
+                result = call.resume(&[Val::I32(errno as u32)]).map_err(call_error)?;
+            };
+            times_to_run.push((Instant::now() - start).as_micros());
+
+            if status != 0 {
+                return Err(Error::check(Exit(status)));
+            }
+        }
+        Ok(crate::utils::command_statistics(bench_name, &times_to_run))
+    }
+}
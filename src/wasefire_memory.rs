@@ -0,0 +1,77 @@
+//! Size of the linear memory lent to the wasefire interpreter, which doesn't allocate it itself. Shared with the host
+//! harness, which includes this file, so that both give a module the same memory.
+
+use core::fmt;
+
+/// Largest linear memory given to a module, which fails to instantiate if its initial size doesn't fit instead of
+/// exhausting the heap. 128 KiB unless `WASEFIRE_MEMORY_BUDGET` gives another number of bytes when building, for the
+/// boards with more RAM
+pub const MEMORY_BUDGET: usize = match option_env!("WASEFIRE_MEMORY_BUDGET") {
+    Some(budget) => match usize::from_str_radix(budget, 10) {
+        Ok(budget) => budget,
+        Err(_) => panic!("WASEFIRE_MEMORY_BUDGET must be a number of bytes"),
+    },
+    None => 2 * PAGE_SIZE,
+};
+
+pub const PAGE_SIZE: usize = 64 * 1024;
+
+/// Initial number of pages of a module, more than [`MEMORY_BUDGET`] holds
+#[derive(Debug)]
+pub struct OverBudget(pub u32);
+
+impl fmt::Display for OverBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the module needs {} pages of linear memory, more than the budget of {MEMORY_BUDGET} bytes", self.0)
+    }
+}
+
+/// Bytes for the declared maximum of the module, so that the pages `memory.grow` adds are backed, or for its initial
+/// size when it doesn't declare one, within [`MEMORY_BUDGET`]. The interpreter reports growing past the memory it was
+/// given as a success, but traps on any access to the pages it couldn't back
+pub fn memory_size(wasm: &[u8]) -> Result<usize, OverBudget> {
+    // 65536 pages don't fit in the address space of the boards
+    let bytes = |pages: u32| (pages as usize).checked_mul(PAGE_SIZE);
+    let (min, max) = memory_limits(wasm).unwrap_or((0, None));
+    let min = bytes(min).filter(|&bytes| bytes <= MEMORY_BUDGET).ok_or(OverBudget(min))?;
+    Ok(max.map_or(min, |max| bytes(max).unwrap_or(usize::MAX)).min(MEMORY_BUDGET))
+}
+
+/// Initial and maximum number of pages declared by the memory section of `wasm`, if it defines a memory
+pub fn memory_limits(wasm: &[u8]) -> Option<(u32, Option<u32>)> {
+    /// Unsigned LEB128 value at `*offset`, which is moved past it
+    fn leb128(wasm: &[u8], offset: &mut usize) -> Option<u32> {
+        let mut value = 0_u32;
+        for shift in (0..35).step_by(7) {
+            let byte = *wasm.get(*offset)?;
+            *offset += 1;
+            value |= u32::from(byte & 0x7f).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    // Skips the magic number and the version
+    let mut offset = 8;
+    while offset < wasm.len() {
+        let id = wasm[offset];
+        offset += 1;
+        let size = leb128(wasm, &mut offset)? as usize;
+        let end = offset.checked_add(size)?;
+        // The memory section, holding a vector of memories of which only the first one can be used
+        if id == 5 {
+            if leb128(wasm, &mut offset)? == 0 {
+                return None;
+            }
+            let flags = *wasm.get(offset)?;
+            offset += 1;
+            let min = leb128(wasm, &mut offset)?;
+            let max = if flags & 1 == 1 { Some(leb128(wasm, &mut offset)?) } else { None };
+            return Some((min, max));
+        }
+        offset = end;
+    }
+    None
+}
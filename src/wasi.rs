@@ -0,0 +1,147 @@
+//! Subset of WASI preview1 shared by the backends, enough for `wasm32-wasip1` commands that print, read the clock and
+//! ask for random bytes. The startup code and the stdio of wasi-libc and Rust's std also ask for the environment and
+//! about the standard streams, which get empty answers. Each backend links these functions under [`MODULE`] and hands
+//! them the linear memory of the calling instance, `proc_exit` being left to them as it has to end the call.
+
+use core::fmt;
+
+use ariel_os::debug::log::debug;
+use ariel_os::time::Instant;
+
+pub const MODULE: &str = "wasi_snapshot_preview1";
+
+// Errors of WASI preview1, only those of the subset
+const SUCCESS: i32 = 0;
+const BADF: i32 = 8;
+const FAULT: i32 = 21;
+const INVAL: i32 = 28;
+
+const STDOUT: i32 = 1;
+const STDERR: i32 = 2;
+
+// Type and rights of a file descriptor, only those the standard streams have
+const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+const RIGHTS_FD_WRITE: u64 = 1 << 6;
+
+// Clocks of WASI preview1, none of the boards has a wall clock, the realtime clock counts from boot like the others
+const CLOCK_REALTIME: i32 = 0;
+const CLOCK_THREAD_CPUTIME: i32 = 3;
+
+/// Status given to `proc_exit`, returned by the host function as an error to unwind the call
+#[derive(Debug)]
+pub struct Exit(pub i32);
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "proc_exit({})", self.0)
+    }
+}
+
+impl core::error::Error for Exit {}
+
+/// Logs the buffers described by the `iovs_len` iovecs at `iovs`, only stdout and stderr can be written to
+pub fn fd_write(memory: &mut [u8], fd: i32, iovs: i32, iovs_len: i32, nwritten: i32) -> i32 {
+    if fd != STDOUT && fd != STDERR {
+        return BADF;
+    }
+    let mut written = 0_u32;
+    for iov in 0..iovs_len as u32 {
+        let Some(iov) = iov.checked_mul(8).and_then(|offset| (iovs as u32).checked_add(offset)) else {
+            return FAULT;
+        };
+        let (Some(buf), Some(len)) = (read_u32(memory, iov), iov.checked_add(4).and_then(|len| read_u32(memory, len))) else {
+            return FAULT;
+        };
+        let Some(bytes) = memory.get(buf as usize..(buf as usize).saturating_add(len as usize)) else {
+            return FAULT;
+        };
+        // One log line per buffer, a line split over several writes is logged in pieces
+        match core::str::from_utf8(bytes) {
+            Ok(text) => debug!("[fd {}] {}", fd, text.trim_end_matches('\n')),
+            Err(_) => debug!("[fd {}] {:?}", fd, bytes),
+        }
+        written = written.wrapping_add(len);
+    }
+    write(memory, nwritten, &written.to_le_bytes())
+}
+
+/// Time since boot in ns, the precision is ignored
+pub fn clock_time_get(memory: &mut [u8], id: i32, _precision: i64, time: i32) -> i32 {
+    if !(CLOCK_REALTIME..=CLOCK_THREAD_CPUTIME).contains(&id) {
+        return INVAL;
+    }
+    let now = Instant::now().as_micros().saturating_mul(1000);
+    write(memory, time, &now.to_le_bytes())
+}
+
+/// Fills the buffer from the random number generator of Ariel OS
+pub fn random_get(memory: &mut [u8], buf: i32, len: i32) -> i32 {
+    use rand_core::RngCore;
+
+    let Some(bytes) = memory.get_mut(buf as u32 as usize..(buf as u32 as usize).saturating_add(len as u32 as usize)) else {
+        return FAULT;
+    };
+    ariel_os::random::fast_rng().fill_bytes(bytes);
+    SUCCESS
+}
+
+/// Commands are given no arguments, there is nothing to write
+pub fn args_get(_memory: &mut [u8], _argv: i32, _argv_buf: i32) -> i32 {
+    SUCCESS
+}
+
+/// No arguments, taking no room, which callers ask before `args_get`
+pub fn args_sizes_get(memory: &mut [u8], argc: i32, argv_buf_size: i32) -> i32 {
+    match write(memory, argc, &0_u32.to_le_bytes()) {
+        SUCCESS => write(memory, argv_buf_size, &0_u32.to_le_bytes()),
+        errno => errno,
+    }
+}
+
+/// Commands are given no environment variables, there is nothing to write
+pub fn environ_get(_memory: &mut [u8], _environ: i32, _environ_buf: i32) -> i32 {
+    SUCCESS
+}
+
+/// No environment variables, taking no room
+pub fn environ_sizes_get(memory: &mut [u8], count: i32, buf_size: i32) -> i32 {
+    args_sizes_get(memory, count, buf_size)
+}
+
+/// stdout and stderr are character devices which can only be written to, so that libc doesn't try to seek them
+pub fn fd_fdstat_get(memory: &mut [u8], fd: i32, stat: i32) -> i32 {
+    if fd != STDOUT && fd != STDERR {
+        return BADF;
+    }
+    // `fdstat`: the file type, flags at 2, then the base and inheriting rights at 8 and 16
+    let mut fdstat = [0; 24];
+    fdstat[0] = FILETYPE_CHARACTER_DEVICE;
+    fdstat[8..16].copy_from_slice(&RIGHTS_FD_WRITE.to_le_bytes());
+    write(memory, stat, &fdstat)
+}
+
+/// Nothing is ever opened, and the standard streams stay open
+pub fn fd_close(_memory: &mut [u8], _fd: i32) -> i32 {
+    BADF
+}
+
+/// None of the file descriptors can be seeked
+pub fn fd_seek(_memory: &mut [u8], _fd: i32, _offset: i64, _whence: i32, _newoffset: i32) -> i32 {
+    BADF
+}
+
+fn read_u32(memory: &[u8], ptr: u32) -> Option<u32> {
+    let bytes = memory.get(ptr as usize..(ptr as usize).checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn write(memory: &mut [u8], ptr: i32, bytes: &[u8]) -> i32 {
+    let ptr = ptr as u32 as usize;
+    match memory.get_mut(ptr..ptr.saturating_add(bytes.len())) {
+        Some(target) => {
+            target.copy_from_slice(bytes);
+            SUCCESS
+        }
+        None => FAULT,
+    }
+}
@@ -3,6 +3,37 @@ use wasm::{validate, Store, ExternVal, Value, HaltExecutionError};
 extern crate alloc;
 use alloc::{vec, vec::Vec};
 
+use crate::error::Error;
+
+/// Calls `function` with fuel, handing it more whenever it runs out, and returns its results along with the fuel it
+/// consumed
+#[cfg(feature = "metering")]
+fn invoke_metered<T: wasm::config::Config>(
+    store: &mut Store<T>,
+    function: wasm::FuncAddr,
+    args: Vec<Value>,
+) -> Result<(Vec<Value>, u64), Error> {
+    use wasm::RunState;
+
+    let mut consumed = 0;
+    let mut state = store.invoke(function, args, Some(u32::MAX)).map_err(Error::trap)?;
+    loop {
+        match state {
+            RunState::Finished { values, maybe_remaining_fuel } => {
+                consumed += u64::from(u32::MAX - maybe_remaining_fuel.unwrap_or(0));
+                return Ok((values, consumed));
+            }
+            RunState::Resumable { mut resumable_ref, .. } => {
+                store.access_fuel_mut(&mut resumable_ref, |fuel| {
+                    consumed += u64::from(u32::MAX - fuel.unwrap_or(0));
+                    *fuel = Some(u32::MAX);
+                }).map_err(Error::host)?;
+                state = store.resume(resumable_ref).map_err(Error::trap)?;
+            }
+        }
+    }
+}
+
 #[cfg(feature = "coremark")]
 pub mod coremark {
     use super::*;
@@ -11,10 +42,10 @@ pub mod coremark {
         Ok(Vec::from_iter(core::iter::once(Value::I64(ariel_os::time::Instant::now().as_millis()))))
     }
 
-    pub fn run_coremark() -> f32 {
+    pub fn run_coremark() -> Result<f32, Error> {
         let wasm_bytes = include_bytes!(crate::benchmark_file!());
 
-        let validation_info = validate(wasm_bytes).unwrap();
+        let validation_info = validate(wasm_bytes).map_err(Error::parse)?;
 
         let mut store = Store::new(());
 
@@ -24,27 +55,23 @@ pub mod coremark {
             &validation_info,
             vec![ExternVal::Func(func_addr)],
             None,
-        ).unwrap();
+        ).map_err(Error::instantiate)?;
 
-        let run_addr = store.instance_export(module.module_addr, "run").unwrap()
+        let run_addr = store.instance_export(module.module_addr, "run").map_err(Error::link)?
             .as_func()
-            .unwrap();
+            .ok_or_else(|| Error::missing_export("run"))?;
 
-        let res: f32 = store.invoke_typed_without_fuel(
-            run_addr, ()
-        ).unwrap();
-        return res
+        store.invoke_typed_without_fuel(run_addr, ()).map_err(Error::trap)
     }
 }
 
 #[cfg(feature = "embench-1")]
 pub mod embench1 {
     use ariel_os::time::Instant;
-    use libm::{pow, exp, log, sqrt};
-    use ariel_os::debug::log::{debug, error};
+    use ariel_os::debug::log::debug;
 
     use super::*;
-    use crate::{BENCH_SCORE, BENCHMARK_LOOPS, benchmark_name, benchmark_file};
+    use crate::{BENCHMARK_LOOPS, benchmark_name, benchmark_file};
 
     struct TimeTracking(Instant, Instant);
 
@@ -52,11 +79,11 @@ pub mod embench1 {
 
     impl Config for TimeTracking {}
 
-    pub fn run_bench() -> (f64, f64, f64, f64) {
+    pub fn run_bench() -> Result<(f64, f64, f64, f64), Error> {
         let bench_name = benchmark_name!();
         let wasm = include_bytes!(benchmark_file!());
 
-        let validation_info = validate(wasm).unwrap();
+        let validation_info = validate(wasm).map_err(Error::parse)?;
 
         let mut store = Store::new(TimeTracking(Instant::now(), Instant::now()));
 
@@ -68,78 +95,376 @@ pub mod embench1 {
             &validation_info,
             vec![ExternVal::Func(board_init), ExternVal::Func(start_trig), ExternVal::Func(stop_trig)],
             None,
-        ).unwrap();
+        ).map_err(Error::instantiate)?;
 
-        let bench_function = store.instance_export(module.module_addr, "__original_main").unwrap()
+        let bench_function = store.instance_export(module.module_addr, "__original_main").map_err(Error::link)?
             .as_func()
-            .unwrap();
+            .ok_or_else(|| Error::missing_export("__original_main"))?;
 
         debug!("Starting wasm app");
         let mut times_to_run = Vec::new();
         for i in 1..=BENCHMARK_LOOPS {
             debug!("Run {}", i);
-            let correct = store.invoke_typed_without_fuel(bench_function, ()).unwrap();
+            // Traps and the errors of host functions can't be told apart
+            #[cfg(not(feature = "metering"))]
+            let correct: i32 = store.invoke_typed_without_fuel(bench_function, ()).map_err(Error::trap)?;
+            #[cfg(feature = "metering")]
+            let (correct, fuel) = {
+                let (results, fuel) = invoke_metered(&mut store, bench_function, Vec::new())?;
+                let &[Value::I32(correct)] = &results[..] else {
+                    return Err(Error::check(&results));
+                };
+                (correct as i32, fuel)
+            };
             match correct {
                 0 => {
                     let &TimeTracking(start, end) = &store.user_data;
                     // The relative speed is in milli seconds
                     times_to_run.push((end - start).as_millis());
+                    // Every run starts from the same state, checking the first one is enough
+                    if i == 1 {
+                        let memory = store.instance_export(module.module_addr, "memory").map_err(Error::link)?
+                            .as_mem()
+                            .ok_or_else(|| Error::missing_export("memory"))?;
+                        store.mem_access_mut_slice(memory, |data| crate::utils::log_checksum(bench_name, data));
+                        #[cfg(feature = "metering")]
+                        crate::utils::log_fuel(bench_name, fuel);
+                    }
                 },
-                _ => {
-                    error!("Benchmarking went wrong for some reason, aborting");
-                    return (0_f64, 0_f64, 0_f64, 0_f64);
-                }
+                _ => return Err(Error::check(correct)),
             }
         }
-        let mut geo_mean = 1_f64;
-        let mut times_geo_mean = 1_f64;
-        let score_to_div = BENCH_SCORE.iter().find(|(b_name, _)| *b_name == bench_name).unwrap().1;
-
-        for dur in times_to_run.iter() {
-            let normalized_speed = score_to_div as f64 / *dur as f64;
-            geo_mean *= pow(normalized_speed as f64, 1_f64/BENCHMARK_LOOPS as f64);
-            times_geo_mean *= pow(*dur as f64, 1_f64/BENCHMARK_LOOPS as f64);
-        }
+        Ok(crate::utils::embench_statistics(bench_name, &times_to_run))
+    }
+
+    fn initialise_board(_: &mut TimeTracking, _: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
+        Ok(Vec::new())
+    }
+
+
+    fn start_trigger(start_end: &mut TimeTracking, _: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
+        start_end.0 = Instant::now();
+        #[cfg(feature = "cycle-count")]
+        crate::utils::cycle_count::start();
+        Ok(Vec::new())
+    }
 
-        // sigma = exp( sqrt( 1/N sum( ln ( A_i / mean )^2 ) ) ) https://en.wikipedia.org/wiki/Geometric_standard_deviation
-        let mut times_geo_std = 0_f64;
-        let mut geo_std = 0_f64;
-        for dur in times_to_run.iter() {
-            let normalized_speed = score_to_div as f64 / *dur as f64;
-            let logged = log(normalized_speed / geo_mean);
-            geo_std += logged * logged;
+    fn stop_trigger(start_end: &mut TimeTracking, _: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
+        #[cfg(feature = "cycle-count")]
+        crate::utils::cycle_count::stop();
+        start_end.1 = Instant::now();
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(feature = "host-calls")]
+pub mod host_calls {
+    use core::hint::black_box;
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use super::*;
+    use crate::{HOST_CALLS, HOST_CALL_LOOPS, benchmark_name, benchmark_file};
 
-            let logged_times = log(*dur as f64 / times_geo_mean);
-            times_geo_std += logged_times * logged_times;
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        // Host functions are only given the user data of the store, neither the memory nor a way to call into wasm
+        if matches!(bench_name, "memory-copy" | "callback") {
+            return Err(Error::Unsupported("host functions can't access the instance".into()));
         }
-        geo_std = exp(sqrt(1_f64 / BENCHMARK_LOOPS as f64 * geo_std));
-        times_geo_std = exp(sqrt(1_f64 / BENCHMARK_LOOPS as f64 * times_geo_std));
 
-        debug!("Benchmark results for {}:", bench_name);
-        debug!("(Geometric) Mean score: {}", geo_mean);
-        debug!("Geometric Standard Deviation Score: {}", geo_std);
-        debug!("Range: [{}, {}]", geo_mean / geo_std, geo_mean * geo_std);
+        let validation_info = validate(wasm).map_err(Error::parse)?;
+
+        let mut store = Store::new(());
+
+        // In the order of the imports of the suite
+        let imports = vec![
+            ExternVal::Func(store.func_alloc_typed::<(), ()>(nop)),
+            ExternVal::Func(store.func_alloc_typed::<i32, ()>(take_value)),
+            ExternVal::Func(store.func_alloc_typed::<i64, ()>(take_value)),
+            ExternVal::Func(store.func_alloc_typed::<f64, ()>(take_value)),
+            ExternVal::Func(store.func_alloc_typed::<(i32, i32), ()>(unsupported)),
+            ExternVal::Func(store.func_alloc_typed::<(), ()>(unsupported)),
+        ];
+
+        let module = store.module_instantiate(&validation_info, imports, None).map_err(Error::instantiate)?;
+
+        let run = store.instance_export(module.module_addr, "run").map_err(Error::link)?
+            .as_func()
+            .ok_or_else(|| Error::missing_export("run"))?;
 
-        debug!("Timing results:");
-        debug!("(Geometric) Mean time to completion: {}ms", times_geo_mean);
-        debug!("Geometric Standard Deviation Time: {}", times_geo_std);
-        debug!("Range(ms): [{}, {}]", times_geo_mean / times_geo_std, times_geo_mean * times_geo_std);
+        debug!("Starting wasm app");
+        let mut times_to_run = Vec::new();
+        for i in 1..=HOST_CALL_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            store.invoke_typed_without_fuel::<i32, ()>(run, HOST_CALLS).map_err(Error::trap)?;
+            times_to_run.push((Instant::now() - start).as_micros());
+        }
+        Ok(crate::utils::rate_statistics(bench_name, HOST_CALLS, &times_to_run))
+    }
 
-        (geo_mean, geo_std, times_geo_mean, times_geo_std)
+    fn nop(_: &mut (), _: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
+        Ok(Vec::new())
     }
 
-    fn initialise_board(_: &mut TimeTracking, _: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
+    fn take_value(_: &mut (), args: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
+        black_box(args);
         Ok(Vec::new())
     }
 
+    fn unsupported(_: &mut (), _: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
+        Err(HaltExecutionError)
+    }
+}
 
-    fn start_trigger(start_end: &mut TimeTracking, _: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
-        start_end.0 = Instant::now();
+#[cfg(feature = "linear-memory")]
+pub mod linear_memory {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use super::*;
+    use crate::{MEMORY_ITERATIONS, MEMORY_LOOPS, benchmark_name, benchmark_file};
+
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let validation_info = validate(wasm).map_err(Error::parse)?;
+
+        let mut store = Store::new(());
+
+        // The modules of the suite import nothing
+        let module = store.module_instantiate(&validation_info, Vec::new(), None).map_err(Error::instantiate)?;
+
+        let run = store.instance_export(module.module_addr, "run").map_err(Error::link)?
+            .as_func()
+            .ok_or_else(|| Error::missing_export("run"))?;
+
+        debug!("Starting wasm app");
+        let mut times_to_run = Vec::new();
+        for i in 1..=MEMORY_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            store.invoke_typed_without_fuel::<i32, ()>(run, MEMORY_ITERATIONS).map_err(Error::trap)?;
+            times_to_run.push((Instant::now() - start).as_micros());
+        }
+        Ok(crate::utils::rate_statistics(bench_name, MEMORY_ITERATIONS, &times_to_run))
+    }
+}
+
+#[cfg(feature = "memory-growth")]
+pub mod memory_growth {
+    use super::*;
+    use crate::{benchmark_file, utils::heap_used};
+
+    /// Pages of the memory before and after growing it by one, and the heap allocated by the growth
+    pub fn run_bench() -> Result<(i32, i32, usize), Error> {
+        let wasm = include_bytes!(benchmark_file!());
+
+        let validation_info = validate(wasm).map_err(Error::parse)?;
+
+        let mut store = Store::new(());
+
+        let module = store.module_instantiate(&validation_info, Vec::new(), None).map_err(Error::instantiate)?;
+
+        let size = store.instance_export(module.module_addr, "size").map_err(Error::link)?
+            .as_func()
+            .ok_or_else(|| Error::missing_export("size"))?;
+        let grow = store.instance_export(module.module_addr, "grow").map_err(Error::link)?
+            .as_func()
+            .ok_or_else(|| Error::missing_export("grow"))?;
+
+        let pages_before: i32 = store.invoke_typed_without_fuel(size, ()).map_err(Error::trap)?;
+        let heap_before = heap_used();
+        store.invoke_typed_without_fuel::<i32, i32>(grow, 1).map_err(Error::trap)?;
+        let heap_grown = heap_used().saturating_sub(heap_before);
+        let pages_after: i32 = store.invoke_typed_without_fuel(size, ()).map_err(Error::trap)?;
+
+        Ok((pages_before, pages_after, heap_grown))
+    }
+}
+
+#[cfg(feature = "preemption")]
+pub mod preemption {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+    use wasm::RunState;
+
+    use super::*;
+    use crate::{PREEMPTION_LOOPS, PREEMPTION_ROUNDS, SLICE_FUEL, benchmark_name, benchmark_file, utils::scheduling};
+
+    pub async fn run_bench() -> Result<(f64, f64, f64, f64, u32), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let validation_info = validate(wasm).map_err(Error::parse)?;
+
+        let mut store = Store::new(());
+
+        let checkpoint = store.func_alloc_typed::<(), ()>(checkpoint);
+
+        let module = store.module_instantiate(&validation_info, vec![ExternVal::Func(checkpoint)], None)
+            .map_err(Error::instantiate)?;
+
+        let run = store.instance_export(module.module_addr, "run").map_err(Error::link)?
+            .as_func()
+            .ok_or_else(|| Error::missing_export("run"))?;
+
+        // The fuel of the interpreter is a `u32`
+        let slice_fuel = SLICE_FUEL as u32;
+
+        debug!("Starting wasm app");
+        let mut runs = Vec::new();
+        for i in 1..=PREEMPTION_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            let expected: i32 = store.invoke_typed_without_fuel(run, PREEMPTION_ROUNDS).map_err(Error::trap)?;
+            let to_completion = (Instant::now() - start).as_micros();
+
+            // Every slice is given the same fuel, and the call is resumed once the other tasks had their turn
+            scheduling::start();
+            let start = Instant::now();
+            let mut slices = 1;
+            let mut state = store.invoke(run, vec![Value::I32(PREEMPTION_ROUNDS as u32)], Some(slice_fuel))
+                .map_err(Error::trap)?;
+            let results = loop {
+                match state {
+                    RunState::Finished { values, .. } => break values,
+                    RunState::Resumable { mut resumable_ref, .. } => {
+                        store.access_fuel_mut(&mut resumable_ref, |fuel| *fuel = Some(slice_fuel)).map_err(Error::host)?;
+                        crate::utils::yield_now().await;
+                        slices += 1;
+                        state = store.resume(resumable_ref).map_err(Error::trap)?;
+                    }
+                }
+            };
+            let sliced = (Instant::now() - start).as_micros();
+            let (lateness_mean, lateness_max) = scheduling::stop().await;
+
+            let &[Value::I32(state)] = &results[..] else {
+                return Err(Error::check(&results));
+            };
+            if state as i32 != expected {
+                return Err(Error::check(state));
+            }
+            runs.push((to_completion, sliced, slices, lateness_mean, lateness_max));
+        }
+        Ok(crate::utils::preemption_statistics(bench_name, &runs))
+    }
+
+    fn checkpoint(_: &mut (), _: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
         Ok(Vec::new())
     }
+}
 
-    fn stop_trigger(start_end: &mut TimeTracking, _: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
-        start_end.1 = Instant::now();
+#[cfg(feature = "async-host")]
+pub mod async_host {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use super::*;
+    use crate::{SLEEPS, SLEEP_LOOPS, SLEEP_MS, benchmark_name, benchmark_file, utils::{host_futures, scheduling}};
+
+    /// Host functions can't be suspended, the sleep blocks the executor until it's over
+    pub async fn run_bench() -> Result<(f64, f64, u32), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let validation_info = validate(wasm).map_err(Error::parse)?;
+
+        let mut store = Store::new(());
+
+        let sleep_ms = store.func_alloc_typed::<i32, ()>(sleep_ms);
+
+        let module = store.module_instantiate(&validation_info, vec![ExternVal::Func(sleep_ms)], None)
+            .map_err(Error::instantiate)?;
+
+        let run = store.instance_export(module.module_addr, "run").map_err(Error::link)?
+            .as_func()
+            .ok_or_else(|| Error::missing_export("run"))?;
+
+        debug!("Starting wasm app");
+        let mut runs = Vec::new();
+        for i in 1..=SLEEP_LOOPS {
+            debug!("Run {}", i);
+            scheduling::start();
+            let start = Instant::now();
+            store.invoke_typed_without_fuel::<(i32, i32), ()>(run, (SLEEPS, SLEEP_MS)).map_err(Error::trap)?;
+            let time = (Instant::now() - start).as_micros();
+            let (lateness_mean, lateness_max) = scheduling::stop().await;
+            runs.push((time, lateness_mean, lateness_max));
+        }
+        Ok(crate::utils::sleep_statistics(bench_name, &runs))
+    }
+
+    fn sleep_ms(_: &mut (), args: Vec<Value>) -> Result<Vec<Value>, HaltExecutionError> {
+        let [Value::I32(ms)] = args[..] else {
+            return Err(HaltExecutionError);
+        };
+        host_futures::sleep_blocking(ms as i32);
         Ok(Vec::new())
     }
 }
+
+#[cfg(feature = "multi-instance")]
+pub mod multi_instance {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use super::*;
+    use crate::{INSTANCES, INSTANCE_ITERATIONS, INSTANCE_ROUNDS, benchmark_name, benchmark_file, utils::heap_used};
+
+    /// Every instance lives in the same store, and shares the validation info of the module with the others
+    pub fn run_bench() -> Result<(usize, usize, usize, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        // Allocated up front to leave it out of the heap measurements
+        let mut runs = Vec::with_capacity(INSTANCES);
+        let heap_start = heap_used();
+
+        let validation_info = validate(wasm).map_err(Error::parse)?;
+
+        let mut store = Store::new(());
+
+        let heap_base = heap_used().saturating_sub(heap_start);
+
+        // The modules of the suite import nothing
+        for _ in 0..INSTANCES {
+            let module = store.module_instantiate(&validation_info, Vec::new(), None).map_err(Error::instantiate)?;
+            runs.push(store.instance_export(module.module_addr, "run").map_err(Error::link)?
+                .as_func()
+                .ok_or_else(|| Error::missing_export("run"))?);
+        }
+        let heap_instances = heap_used().saturating_sub(heap_start + heap_base);
+
+        debug!("Starting wasm apps");
+        let mut totals = [0; INSTANCES];
+        let start = Instant::now();
+        for i in 1..=INSTANCE_ROUNDS {
+            debug!("Round {}", i);
+            for (index, &run) in runs.iter().enumerate() {
+                totals[index] = store.invoke_typed_without_fuel::<(i32, i32), i32>(run, (index as i32 + 1, INSTANCE_ITERATIONS))
+                    .map_err(Error::trap)?;
+            }
+        }
+        let time = (Instant::now() - start).as_micros();
+
+        crate::utils::check_totals(&totals)?;
+        Ok(crate::utils::instance_statistics(bench_name, heap_base, heap_instances, time))
+    }
+}
+
+#[cfg(feature = "wasi")]
+pub mod wasi {
+    use super::*;
+
+    /// Every function of the subset but `proc_exit` writes to the linear memory of the caller
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        Err(Error::Unsupported("host functions can't access the instance".into()))
+    }
+}
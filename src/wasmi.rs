@@ -1,121 +1,553 @@
 use wasmi::{Caller, Config, Engine, Linker, Module, Store};
+#[cfg(any(feature = "wasmi-eager", feature = "wasmi-lazy"))]
+use wasmi::CompilationMode;
+
+use crate::error::Error;
+
+/// Builds the engine configuration selected by the `wasmi-*` features.
+/// Without any of them this is wasmi's default (lazy translation, eager validation).
+fn engine_config() -> Config {
+    #[allow(unused_mut)]
+    let mut config = Config::default();
+
+    #[cfg(feature = "wasmi-eager")]
+    config.compilation_mode(CompilationMode::Eager);
+
+    #[cfg(feature = "wasmi-lazy")]
+    config.compilation_mode(CompilationMode::Lazy);
+
+    #[cfg(any(feature = "wasmi-fuel", feature = "metering", feature = "preemption"))]
+    config.consume_fuel(true);
+
+    // Float instructions are only rejected when the module is validated, which the feature enables.
+    #[cfg(feature = "wasmi-no-floats")]
+    config.floats(false);
+
+    config
+}
+
+/// Parses the module, validating it only when `wasmi-checked` is enabled.
+fn load_module(engine: &Engine, wasm: &[u8]) -> Result<Module, Error> {
+    #[cfg(feature = "wasmi-checked")]
+    let module = Module::new(engine, wasm);
+
+    #[cfg(not(feature = "wasmi-checked"))]
+    let module = unsafe { Module::new_unchecked(engine, wasm) };
+
+    module.map_err(Error::parse)
+}
+
+/// Splits the traps, running out of fuel included, from the errors returned by host functions
+fn call_error(error: wasmi::Error) -> Error {
+    match error.as_trap_code() {
+        Some(code) => Error::trap(code),
+        None => Error::host(error),
+    }
+}
+
+/// Creates a store, giving it as much fuel as possible when fuel metering is enabled.
+fn new_store<T>(engine: &Engine, data: T) -> Store<T> {
+    #[allow(unused_mut)]
+    let mut store = Store::new(engine, data);
+
+    #[cfg(any(feature = "wasmi-fuel", feature = "metering", feature = "preemption"))]
+    store.set_fuel(u64::MAX).unwrap();
+
+    store
+}
 
 #[cfg(feature = "coremark")]
 pub mod coremark {
     use super::*;
-    pub fn run_coremark() -> f32 {
+    pub fn run_coremark() -> Result<f32, Error> {
 
         let wasm = include_bytes!(crate::benchmark_file!());
 
-        let config = Config::default();
-
-        // config.floats(false);
+        let config = engine_config();
 
         let engine = Engine::new(&config);
-        let mut store = Store::new(&engine, ());
+        let mut store = new_store(&engine, ());
 
-        let module = unsafe { Module::new_unchecked(&engine, wasm).unwrap() };
+        let module = load_module(&engine, wasm)?;
 
         let mut linker = Linker::new(&engine);
 
-        linker.func_wrap("env", "clock_ms", |_: Caller<'_, _>| { ariel_os::time::Instant::now().as_millis() }).unwrap();
+        linker.func_wrap("env", "clock_ms", |_: Caller<'_, _>| { ariel_os::time::Instant::now().as_millis() })
+            .map_err(Error::link)?;
 
-        let instance = linker.instantiate_and_start(&mut store, &module).unwrap();
+        let instance = linker.instantiate_and_start(&mut store, &module).map_err(Error::instantiate)?;
 
-        instance.get_typed_func::<(), f32>(&mut store, "run").unwrap()
-            .call(&mut store, ()).unwrap()
+        instance.get_typed_func::<(), f32>(&mut store, "run").map_err(Error::link)?
+            .call(&mut store, ()).map_err(call_error)
     }
 }
 
 #[cfg(feature = "embench-1")]
 pub mod embench1 {
     use ariel_os::time::Instant;
-    use libm::{pow, exp, log, sqrt};
-    use ariel_os::debug::log::{debug, error};
+    use ariel_os::debug::log::debug;
 
     use super::*;
-    use crate::{BENCH_SCORE, BENCHMARK_LOOPS, benchmark_name, benchmark_file};
+    use crate::{BENCHMARK_LOOPS, benchmark_name, benchmark_file};
 
     extern crate alloc;
     use alloc::vec::Vec;
 
-    pub fn run_bench() -> (f64, f64, f64, f64) {
+    pub fn run_bench() -> Result<(f64, f64, f64, f64), Error> {
         let bench_name = benchmark_name!();
         let wasm = include_bytes!(benchmark_file!());
 
-        let config = Config::default();
+        let config = engine_config();
 
         let engine = Engine::new(&config);
 
-        let mut store = Store::new(&engine, (Instant::now(), Instant::now()));
+        let mut store = new_store(&engine, (Instant::now(), Instant::now()));
 
-        let module = unsafe { Module::new_unchecked(&engine, wasm).unwrap() };
+        let module = load_module(&engine, wasm)?;
 
         let mut linker = Linker::new(&engine);
 
-        linker.func_wrap("env", "initialise_board", || {}).unwrap();
+        linker.func_wrap("env", "initialise_board", || {}).map_err(Error::link)?;
         linker.func_wrap("env", "start_trigger", |mut c: Caller<'_, (Instant, Instant)>| {
             c.data_mut().0 = Instant::now();
-        }).unwrap();
+            #[cfg(feature = "cycle-count")]
+            crate::utils::cycle_count::start();
+        }).map_err(Error::link)?;
         linker.func_wrap("env", "stop_trigger", |mut c: Caller<'_, (Instant, Instant)>| {
+            #[cfg(feature = "cycle-count")]
+            crate::utils::cycle_count::stop();
             c.data_mut().1 = Instant::now();
-        }).unwrap();
+        }).map_err(Error::link)?;
 
-        let instance = linker.instantiate_and_start(&mut store, &module).unwrap();
+        let instance = linker.instantiate_and_start(&mut store, &module).map_err(Error::instantiate)?;
+        let main = instance.get_typed_func::<(), u32>(&mut store, "__original_main").map_err(Error::link)?;
 
         let mut times_to_run = Vec::new();
 
         debug!("Starting wasm app");
         for i in 1..=BENCHMARK_LOOPS {
             debug!("Run {}", i);
-            let correct = instance.get_typed_func::<(), u32>(&mut store, "__original_main").unwrap()
-                .call(&mut store, ()).unwrap();
+            #[cfg(feature = "metering")]
+            let fuel = store.get_fuel().map_err(Error::host)?;
+            let correct = main.call(&mut store, ()).map_err(call_error)?;
             match correct {
                 0 => {
                     let &(start, end) = store.data();
                     // The relative speed is in milli seconds
                     times_to_run.push((end - start).as_millis());
+                    // Every run starts from the same state, checking the first one is enough
+                    if i == 1 {
+                        let memory = instance.get_memory(&mut store, "memory")
+                            .ok_or_else(|| Error::missing_export("memory"))?;
+                        crate::utils::log_checksum(bench_name, memory.data(&store));
+                        #[cfg(feature = "metering")]
+                        crate::utils::log_fuel(bench_name, fuel - store.get_fuel().map_err(Error::host)?);
+                    }
                 },
-                _ => {
-                    error!("Benchmarking went wrong for some reason, aborting");
-                    return (0_f64, 0_f64, 0_f64, 0_f64);
+                _ => return Err(Error::check(correct)),
+            }
+        }
+        Ok(crate::utils::embench_statistics(bench_name, &times_to_run))
+    }
+}
+#[cfg(feature = "host-calls")]
+pub mod host_calls {
+    use core::hint::black_box;
+
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+    use wasmi::Extern;
+
+    use super::*;
+    use crate::{HOST_CALLS, HOST_CALL_LOOPS, HOST_COPY_SIZE, benchmark_name, benchmark_file};
+
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let config = engine_config();
+
+        let engine = Engine::new(&config);
+
+        let mut store = new_store(&engine, ());
+
+        let module = load_module(&engine, wasm)?;
+
+        let mut linker = Linker::new(&engine);
+
+        linker.func_wrap("env", "nop", || {}).map_err(Error::link)?;
+        linker.func_wrap("env", "take_i32", |value: i32| { black_box(value); }).map_err(Error::link)?;
+        linker.func_wrap("env", "take_i64", |value: i64| { black_box(value); }).map_err(Error::link)?;
+        linker.func_wrap("env", "take_f64", |value: f64| { black_box(value); }).map_err(Error::link)?;
+        linker.func_wrap("env", "copy", |caller: Caller<'_, ()>, ptr: u32, len: u32| -> Result<(), wasmi::Error> {
+            let mut buffer = [0; HOST_COPY_SIZE];
+            let buffer = buffer.get_mut(..len as usize).ok_or_else(|| wasmi::Error::new("copy larger than the host buffer"))?;
+            let memory = caller.get_export("memory").and_then(Extern::into_memory)
+                .ok_or_else(|| wasmi::Error::new("no memory to copy from"))?;
+            memory.read(&caller, ptr as usize, buffer).map_err(|_| wasmi::Error::new("copy out of bounds"))?;
+            black_box(buffer);
+            Ok(())
+        }).map_err(Error::link)?;
+        linker.func_wrap("env", "call_back", |mut caller: Caller<'_, ()>| -> Result<(), wasmi::Error> {
+            let callback = caller.get_export("callback").and_then(Extern::into_func)
+                .ok_or_else(|| wasmi::Error::new("no callback to call"))?;
+            callback.call(&mut caller, &[], &mut [])
+        }).map_err(Error::link)?;
+
+        let instance = linker.instantiate_and_start(&mut store, &module).map_err(Error::instantiate)?;
+        let run = instance.get_typed_func::<i32, ()>(&mut store, "run").map_err(Error::link)?;
+
+        let mut times_to_run = Vec::new();
+
+        debug!("Starting wasm app");
+        for i in 1..=HOST_CALL_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            run.call(&mut store, HOST_CALLS).map_err(call_error)?;
+            times_to_run.push((Instant::now() - start).as_micros());
+        }
+        Ok(crate::utils::rate_statistics(bench_name, HOST_CALLS, &times_to_run))
+    }
+}
+
+#[cfg(feature = "linear-memory")]
+pub mod linear_memory {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use super::*;
+    use crate::{MEMORY_ITERATIONS, MEMORY_LOOPS, benchmark_name, benchmark_file};
+
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let config = engine_config();
+
+        let engine = Engine::new(&config);
+
+        let mut store = new_store(&engine, ());
+
+        let module = load_module(&engine, wasm)?;
+
+        // The modules of the suite import nothing
+        let linker = Linker::new(&engine);
+
+        let instance = linker.instantiate_and_start(&mut store, &module).map_err(Error::instantiate)?;
+        let run = instance.get_typed_func::<i32, ()>(&mut store, "run").map_err(Error::link)?;
+
+        let mut times_to_run = Vec::new();
+
+        debug!("Starting wasm app");
+        for i in 1..=MEMORY_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            run.call(&mut store, MEMORY_ITERATIONS).map_err(call_error)?;
+            times_to_run.push((Instant::now() - start).as_micros());
+        }
+        Ok(crate::utils::rate_statistics(bench_name, MEMORY_ITERATIONS, &times_to_run))
+    }
+}
+
+#[cfg(feature = "memory-growth")]
+pub mod memory_growth {
+    use super::*;
+    use crate::{benchmark_file, utils::heap_used};
+
+    /// Pages of the memory before and after growing it by one, and the heap allocated by the growth
+    pub fn run_bench() -> Result<(i32, i32, usize), Error> {
+        let wasm = include_bytes!(benchmark_file!());
+
+        let config = engine_config();
+
+        let engine = Engine::new(&config);
+
+        let mut store = new_store(&engine, ());
+
+        let module = load_module(&engine, wasm)?;
+
+        let linker = Linker::new(&engine);
+
+        let instance = linker.instantiate_and_start(&mut store, &module).map_err(Error::instantiate)?;
+        let size = instance.get_typed_func::<(), i32>(&mut store, "size").map_err(Error::link)?;
+        let grow = instance.get_typed_func::<i32, i32>(&mut store, "grow").map_err(Error::link)?;
+
+        let pages_before = size.call(&mut store, ()).map_err(call_error)?;
+        let heap_before = heap_used();
+        grow.call(&mut store, 1).map_err(call_error)?;
+        let heap_grown = heap_used().saturating_sub(heap_before);
+        let pages_after = size.call(&mut store, ()).map_err(call_error)?;
+
+        Ok((pages_before, pages_after, heap_grown))
+    }
+}
+
+#[cfg(feature = "preemption")]
+pub mod preemption {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+    use wasmi::TypedResumableCall;
+
+    use super::*;
+    use crate::{PREEMPTION_LOOPS, PREEMPTION_ROUNDS, SLICE_FUEL, benchmark_name, benchmark_file, utils::scheduling};
+
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    pub async fn run_bench() -> Result<(f64, f64, f64, f64, u32), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let config = engine_config();
+
+        let engine = Engine::new(&config);
+
+        let mut store = new_store(&engine, ());
+
+        let module = load_module(&engine, wasm)?;
+
+        let mut linker = Linker::new(&engine);
+
+        linker.func_wrap("env", "checkpoint", || {}).map_err(Error::link)?;
+
+        let instance = linker.instantiate_and_start(&mut store, &module).map_err(Error::instantiate)?;
+        let run = instance.get_typed_func::<i32, i32>(&mut store, "run").map_err(Error::link)?;
+
+        debug!("Starting wasm app");
+        let mut runs = Vec::new();
+        for i in 1..=PREEMPTION_LOOPS {
+            debug!("Run {}", i);
+            let start = Instant::now();
+            let expected = run.call(&mut store, PREEMPTION_ROUNDS).map_err(call_error)?;
+            let to_completion = (Instant::now() - start).as_micros();
+
+            // Every slice is given the same fuel, and the call is resumed once the other tasks had their turn
+            scheduling::start();
+            let start = Instant::now();
+            let mut slices = 1;
+            store.set_fuel(SLICE_FUEL).map_err(Error::host)?;
+            let mut call = run.call_resumable(&mut store, PREEMPTION_ROUNDS).map_err(call_error)?;
+            let state = loop {
+                match call {
+                    TypedResumableCall::Finished(state) => break state,
+                    TypedResumableCall::OutOfFuel(invocation) => {
+                        store.set_fuel(SLICE_FUEL.max(invocation.required_fuel())).map_err(Error::host)?;
+                        crate::utils::yield_now().await;
+                        slices += 1;
+                        call = invocation.resume(&mut store).map_err(call_error)?;
+                    }
+                    TypedResumableCall::HostTrap(invocation) => return Err(Error::host(invocation.host_error())),
                 }
+            };
+            let sliced = (Instant::now() - start).as_micros();
+            let (lateness_mean, lateness_max) = scheduling::stop().await;
+            store.set_fuel(u64::MAX).map_err(Error::host)?;
+
+            if state != expected {
+                return Err(Error::check(state));
             }
+            runs.push((to_completion, sliced, slices, lateness_mean, lateness_max));
         }
-        let mut geo_mean = 1_f64;
-        let mut times_geo_mean = 1_f64;
-        let score_to_div = BENCH_SCORE.iter().find(|(b_name, _)| *b_name == bench_name).unwrap().1;
-
-        for dur in times_to_run.iter() {
-            let normalized_speed = score_to_div as f64 / *dur as f64;
-            geo_mean *= pow(normalized_speed as f64, 1_f64/BENCHMARK_LOOPS as f64);
-            times_geo_mean *= pow(*dur as f64, 1_f64/BENCHMARK_LOOPS as f64);
+        Ok(crate::utils::preemption_statistics(bench_name, &runs))
+    }
+}
+
+#[cfg(feature = "async-host")]
+pub mod async_host {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+
+    use super::*;
+    use crate::{SLEEPS, SLEEP_LOOPS, SLEEP_MS, benchmark_name, benchmark_file, utils::{host_futures, scheduling}};
+
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    /// Host functions can't be suspended, the sleep blocks the executor until it's over
+    pub async fn run_bench() -> Result<(f64, f64, u32), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let config = engine_config();
+
+        let engine = Engine::new(&config);
+
+        let mut store = new_store(&engine, ());
+
+        let module = load_module(&engine, wasm)?;
+
+        let mut linker = Linker::new(&engine);
+
+        linker.func_wrap("env", "sleep_ms", host_futures::sleep_blocking)
+            .map_err(Error::link)?;
+
+        let instance = linker.instantiate_and_start(&mut store, &module).map_err(Error::instantiate)?;
+        let run = instance.get_typed_func::<(i32, i32), ()>(&mut store, "run").map_err(Error::link)?;
+
+        debug!("Starting wasm app");
+        let mut runs = Vec::new();
+        for i in 1..=SLEEP_LOOPS {
+            debug!("Run {}", i);
+            scheduling::start();
+            let start = Instant::now();
+            run.call(&mut store, (SLEEPS, SLEEP_MS)).map_err(call_error)?;
+            let time = (Instant::now() - start).as_micros();
+            let (lateness_mean, lateness_max) = scheduling::stop().await;
+            runs.push((time, lateness_mean, lateness_max));
         }
+        Ok(crate::utils::sleep_statistics(bench_name, &runs))
+    }
+}
 
-        // sigma = exp( sqrt( 1/N sum( ln ( A_i / mean )^2 ) ) ) https://en.wikipedia.org/wiki/Geometric_standard_deviation
-        let mut times_geo_std = 0_f64;
-        let mut geo_std = 0_f64;
-        for dur in times_to_run.iter() {
-            let normalized_speed = score_to_div as f64 / *dur as f64;
-            let logged = log(normalized_speed / geo_mean);
-            geo_std += logged * logged;
+#[cfg(feature = "multi-instance")]
+pub mod multi_instance {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
 
-            let logged_times = log(*dur as f64 / times_geo_mean);
-            times_geo_std += logged_times * logged_times;
+    use super::*;
+    use crate::{INSTANCES, INSTANCE_ITERATIONS, INSTANCE_ROUNDS, benchmark_name, benchmark_file, utils::heap_used};
+
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    /// Every instance lives in the same store, and shares the module and its compiled code with the others
+    pub fn run_bench() -> Result<(usize, usize, usize, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        // Allocated up front to leave it out of the heap measurements
+        let mut runs = Vec::with_capacity(INSTANCES);
+        let heap_start = heap_used();
+
+        let config = engine_config();
+
+        let engine = Engine::new(&config);
+
+        let mut store = new_store(&engine, ());
+
+        let module = load_module(&engine, wasm)?;
+
+        // The modules of the suite import nothing
+        let linker = Linker::new(&engine);
+
+        let heap_base = heap_used().saturating_sub(heap_start);
+
+        for _ in 0..INSTANCES {
+            let instance = linker.instantiate_and_start(&mut store, &module).map_err(Error::instantiate)?;
+            runs.push(instance.get_typed_func::<(i32, i32), i32>(&mut store, "run").map_err(Error::link)?);
+        }
+        let heap_instances = heap_used().saturating_sub(heap_start + heap_base);
+
+        debug!("Starting wasm apps");
+        let mut totals = [0; INSTANCES];
+        let start = Instant::now();
+        for i in 1..=INSTANCE_ROUNDS {
+            debug!("Round {}", i);
+            for (index, run) in runs.iter().enumerate() {
+                totals[index] = run.call(&mut store, (index as i32 + 1, INSTANCE_ITERATIONS)).map_err(call_error)?;
+            }
         }
-        geo_std = exp(sqrt(1_f64 / BENCHMARK_LOOPS as f64 * geo_std));
-        times_geo_std = exp(sqrt(1_f64 / BENCHMARK_LOOPS as f64 * times_geo_std));
+        let time = (Instant::now() - start).as_micros();
 
-        debug!("Benchmark results for {}:", bench_name);
-        debug!("(Geometric) Mean score: {}", geo_mean);
-        debug!("Geometric Standard Deviation Score: {}", geo_std);
-        debug!("Range: [{}, {}]", geo_mean / geo_std, geo_mean * geo_std);
+        crate::utils::check_totals(&totals)?;
+        Ok(crate::utils::instance_statistics(bench_name, heap_base, heap_instances, time))
+    }
+}
+
+#[cfg(feature = "wasi")]
+pub mod wasi {
+    use ariel_os::time::Instant;
+    use ariel_os::debug::log::debug;
+    use wasmi::Extern;
 
-        debug!("Timing results:");
-        debug!("(Geometric) Mean time to completion: {}ms", times_geo_mean);
-        debug!("Geometric Standard Deviation Time: {}", times_geo_std);
-        debug!("Range(ms): [{}, {}]", times_geo_mean / times_geo_std, times_geo_mean * times_geo_std);
+    use super::*;
+    use crate::{WASI_LOOPS, benchmark_name, benchmark_file, wasi::{Exit, MODULE}};
 
-        (geo_mean, geo_std, times_geo_mean, times_geo_std)
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    pub fn run_bench() -> Result<(f64, f64), Error> {
+        let bench_name = benchmark_name!();
+        let wasm = include_bytes!(benchmark_file!());
+
+        let config = engine_config();
+
+        let engine = Engine::new(&config);
+
+        let module = load_module(&engine, wasm)?;
+
+        let mut linker = Linker::new(&engine);
+
+        linker.func_wrap(MODULE, "fd_write", |mut caller: Caller<'_, ()>, fd: i32, iovs: i32, iovs_len: i32, nwritten: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::fd_write(memory(&mut caller)?, fd, iovs, iovs_len, nwritten))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "clock_time_get", |mut caller: Caller<'_, ()>, id: i32, precision: i64, time: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::clock_time_get(memory(&mut caller)?, id, precision, time))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "random_get", |mut caller: Caller<'_, ()>, buf: i32, len: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::random_get(memory(&mut caller)?, buf, len))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "args_get", |mut caller: Caller<'_, ()>, argv: i32, argv_buf: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::args_get(memory(&mut caller)?, argv, argv_buf))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "args_sizes_get", |mut caller: Caller<'_, ()>, argc: i32, argv_buf_size: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::args_sizes_get(memory(&mut caller)?, argc, argv_buf_size))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "environ_get", |mut caller: Caller<'_, ()>, environ: i32, environ_buf: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::environ_get(memory(&mut caller)?, environ, environ_buf))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "environ_sizes_get", |mut caller: Caller<'_, ()>, count: i32, buf_size: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::environ_sizes_get(memory(&mut caller)?, count, buf_size))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "fd_fdstat_get", |mut caller: Caller<'_, ()>, fd: i32, stat: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::fd_fdstat_get(memory(&mut caller)?, fd, stat))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "fd_close", |mut caller: Caller<'_, ()>, fd: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::fd_close(memory(&mut caller)?, fd))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "fd_seek", |mut caller: Caller<'_, ()>, fd: i32, offset: i64, whence: i32, newoffset: i32| -> Result<i32, wasmi::Error> {
+            Ok(crate::wasi::fd_seek(memory(&mut caller)?, fd, offset, whence, newoffset))
+        }).map_err(Error::link)?;
+        linker.func_wrap(MODULE, "proc_exit", |status: i32| -> Result<(), wasmi::Error> {
+            Err(wasmi::Error::i32_exit(status))
+        }).map_err(Error::link)?;
+
+        debug!("Starting wasm app");
+        let mut times_to_run = Vec::new();
+        for i in 1..=WASI_LOOPS {
+            debug!("Run {}", i);
+            // A command is only started once, every run gets an instance of its own
+            let mut store = new_store(&engine, ());
+            let instance = linker.instantiate_and_start(&mut store, &module).map_err(Error::instantiate)?;
+            let main = instance.get_typed_func::<(), ()>(&mut store, "_start").map_err(Error::link)?;
+
+            let start = Instant::now();
+            let status = match main.call(&mut store, ()) {
+                Ok(()) => 0,
+                Err(error) => match error.i32_exit_status() {
+                    Some(status) => status,
+                    None => return Err(call_error(error)),
+                },
+            };
+            times_to_run.push((Instant::now() - start).as_micros());
+
+            if status != 0 {
+                return Err(Error::check(Exit(status)));
+            }
+        }
+        Ok(crate::utils::command_statistics(bench_name, &times_to_run))
     }
-}
\ No newline at end of file
+
+    /// Linear memory of the calling instance, which commands export as `memory`
+    fn memory<'a>(caller: &'a mut Caller<'_, ()>) -> Result<&'a mut [u8], wasmi::Error> {
+        let memory = caller.get_export("memory").and_then(Extern::into_memory)
+            .ok_or_else(|| wasmi::Error::new("no memory to access"))?;
+        Ok(memory.data_mut(caller))
+    }
+}